@@ -7,7 +7,7 @@ use stm32f0xx_hal as hal;
 
 use crate::hal::{
     gpio::*,
-    pac::{interrupt, Interrupt, Peripherals, TIM7},
+    pac::{interrupt, Peripherals, TIM7},
     prelude::*,
     time::Hertz,
     timers::*,
@@ -79,16 +79,17 @@ fn main() -> ! {
             // Generate an interrupt when the timer expires
             timer.listen(Event::TimeOut);
 
-            // Move the timer into our global storage
-            *GINT.borrow(cs).borrow_mut() = Some(timer);
-
             // Enable TIM7 IRQ, set prio 1 and clear any pending IRQs
+            let irq = timer.interrupt();
+            timer.unpend();
             let mut nvic = cp.NVIC;
             unsafe {
-                nvic.set_priority(Interrupt::TIM7, 1);
-                cortex_m::peripheral::NVIC::unmask(Interrupt::TIM7);
+                nvic.set_priority(irq, 1);
+                cortex_m::peripheral::NVIC::unmask(irq);
             }
-            cortex_m::peripheral::NVIC::unpend(Interrupt::TIM7);
+
+            // Move the timer into our global storage
+            *GINT.borrow(cs).borrow_mut() = Some(timer);
         });
     }
 
@@ -1,6 +1,7 @@
 //! # API for the Analog to Digital converter
 //!
-//! Currently implements oneshot conversion with variable sampling times.
+//! Currently implements oneshot conversion with variable sampling times and
+//! timer triggered conversion of channel sequences.
 //! Also references for the internal temperature sense, voltage
 //! reference and battery sense are provided.
 //!
@@ -47,6 +48,7 @@ use core::ptr;
 use embedded_hal::{
     adc::{Channel, OneShot},
     blocking::delay::DelayUs,
+    digital::v2::OutputPin,
 };
 
 use crate::{
@@ -68,6 +70,22 @@ pub struct Adc {
     sample_time: AdcSampleTime,
     align: AdcAlign,
     precision: AdcPrecision,
+    trigger_edge: AdcTriggerEdge,
+    overrun_mode: AdcOverrunMode,
+    // Channels of the running sequence and the index of the next result
+    sequence: u32,
+    sequence_pos: u8,
+    // VDDA measurement reused by `read_abs_mv`, see `set_vdda_refresh`
+    vdda_refresh: u16,
+    vdda_cache: Option<(u16, u16)>,
+}
+
+/// ADC error
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// A conversion result was not read before the next one completed
+    Overrun,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -186,6 +204,160 @@ impl From<AdcPrecision> for RES_A {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// ADC external trigger source
+///
+/// Timer events which can start a conversion (or conversion sequence) when
+/// hardware triggering is used.
+pub enum AdcTrigger {
+    /// TIM1 TRGO event (TRG0)
+    Tim1Trgo,
+    /// TIM1 CC4 event (TRG1)
+    ///
+    /// Channel 4 can be configured without a pin by passing
+    /// [NoPin](crate::timers::NoPin) to the PWM constructor, its duty sets the
+    /// sampling point within the PWM period.
+    Tim1Cc4,
+    /// TIM2 TRGO event (TRG2)
+    Tim2Trgo,
+    /// TIM3 TRGO event (TRG3)
+    Tim3Trgo,
+    /// TIM15 TRGO event (TRG4)
+    Tim15Trgo,
+}
+
+impl AdcTrigger {
+    fn extsel_bits(self) -> u32 {
+        match self {
+            AdcTrigger::Tim1Trgo => 0b000,
+            AdcTrigger::Tim1Cc4 => 0b001,
+            AdcTrigger::Tim2Trgo => 0b010,
+            AdcTrigger::Tim3Trgo => 0b011,
+            AdcTrigger::Tim15Trgo => 0b100,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// ADC external trigger edge
+///
+/// Selects on which edge of the [AdcTrigger](crate::adc::AdcTrigger) signal a
+/// conversion is started.
+pub enum AdcTriggerEdge {
+    /// Conversion starts on the rising edge
+    Rising,
+    /// Conversion starts on the falling edge
+    ///
+    /// Triggering on the falling edge of a PWM output compare signal samples
+    /// in the middle of the off-period, e.g. for low side current sensing.
+    Falling,
+    /// Conversion starts on both edges
+    Both,
+}
+
+impl AdcTriggerEdge {
+    /// Get the default trigger edge (currently rising edge)
+    pub fn default() -> Self {
+        AdcTriggerEdge::Rising
+    }
+
+    fn exten_bits(self) -> u32 {
+        match self {
+            AdcTriggerEdge::Rising => 0b01,
+            AdcTriggerEdge::Falling => 0b10,
+            AdcTriggerEdge::Both => 0b11,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// ADC data register behaviour on overrun
+pub enum AdcOverrunMode {
+    /// Keep the old, unread result in the data register
+    Preserve,
+    /// Overwrite the data register with the latest result
+    Overwrite,
+}
+
+impl AdcOverrunMode {
+    /// Get the default overrun mode (currently preserve old data)
+    pub fn default() -> Self {
+        AdcOverrunMode::Preserve
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// ADC clock source
+///
+/// The synchronous PCLK derived clocks remove the conversion start jitter
+/// relative to timer triggers.
+pub enum AdcClockMode {
+    /// Asynchronous clock from the dedicated 14MHz HSI14 oscillator
+    Hsi14,
+    /// Synchronous clock, PCLK divided by 2
+    ///
+    /// PCLK must not exceed 28MHz in this mode. The ADC needs a PCLK with a
+    /// 50% duty cycle, so the APB must not be prescaled (PCLK equal to HCLK),
+    /// the ADC constructors panic otherwise.
+    PclkDiv2,
+    /// Synchronous clock, PCLK divided by 4
+    PclkDiv4,
+}
+
+impl AdcClockMode {
+    /// Get the default clock mode (currently HSI14)
+    pub fn default() -> Self {
+        AdcClockMode::Hsi14
+    }
+
+    fn ckmode_bits(self) -> u32 {
+        match self {
+            AdcClockMode::Hsi14 => 0b00,
+            AdcClockMode::PclkDiv2 => 0b01,
+            AdcClockMode::PclkDiv4 => 0b10,
+        }
+    }
+}
+
+/// Initial ADC configuration for [Adc::new_with_config](crate::adc::Adc::new_with_config)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdcConfig {
+    /// Sampling time
+    pub sample_time: AdcSampleTime,
+    /// Result precision
+    pub precision: AdcPrecision,
+    /// Result alignment
+    pub align: AdcAlign,
+    /// Clock source
+    pub clock_mode: AdcClockMode,
+    /// External trigger edge
+    pub trigger_edge: AdcTriggerEdge,
+    /// Data register behaviour on overrun
+    pub overrun_mode: AdcOverrunMode,
+}
+
+impl Default for AdcConfig {
+    fn default() -> Self {
+        AdcConfig {
+            sample_time: AdcSampleTime::default(),
+            precision: AdcPrecision::default(),
+            align: AdcAlign::default(),
+            clock_mode: AdcClockMode::default(),
+            trigger_edge: AdcTriggerEdge::default(),
+            overrun_mode: AdcOverrunMode::default(),
+        }
+    }
+}
+
+// CFGR2 clock mode field
+const CFGR2_CKMODE_SHIFT: u32 = 30;
+
+// CFGR1 external trigger fields
+const CFGR1_EXTSEL_SHIFT: u32 = 6;
+const CFGR1_EXTSEL_MASK: u32 = 0b111 << CFGR1_EXTSEL_SHIFT;
+const CFGR1_EXTEN_SHIFT: u32 = 10;
+const CFGR1_EXTEN_MASK: u32 = 0b11 << CFGR1_EXTEN_SHIFT;
+
 macro_rules! adc_pins {
     ($($pin:ty => $chan:expr),+ $(,)*) => {
         $(
@@ -269,21 +441,39 @@ impl VTemp {
         adc.rb.ccr.read().tsen().bit_is_set()
     }
 
-    fn convert_temp(vtemp: u16, vdda: u16) -> i16 {
+    /// Enables the internal temperature sense until the returned guard is
+    /// dropped. The t<sub>START</sub> wake up time still applies.
+    pub fn enabled(adc: &mut Adc) -> Enabled<'_, Self> {
+        Enabled::new(adc, Self::new())
+    }
+
+    fn scaled_temp(vtemp: u16, vdda: u16, scale: i32) -> i32 {
         let vtemp30_cal = unsafe { ptr::read(VTEMPCAL30) } as i32;
         let vtemp110_cal = unsafe { ptr::read(VTEMPCAL110) } as i32;
         let raw_temp_comp = vtemp as u32 * vdda as u32 / VDD_CALIB as u32;
-        ((raw_temp_comp as i32 - vtemp30_cal) * 10 * (110 - 30) / (vtemp110_cal - vtemp30_cal)
-            + 300) as i16
+        (raw_temp_comp as i32 - vtemp30_cal) * scale * (110 - 30) / (vtemp110_cal - vtemp30_cal)
+            + 30 * scale
     }
 
-    /// Read the value of the internal temperature sensor and return the
-    /// result in 10ths of a degree centigrade.
+    /// Converts a raw temperature sensor sample into 10ths of a degree
+    /// centigrade.
     ///
-    /// Given a delay reference it will attempt to restrict to the
-    /// minimum delay needed to ensure a 10 us t<sub>START</sub> value.
-    /// Otherwise it will approximate the required delay using ADC reads.
-    pub fn read(adc: &mut Adc, delay: Option<&mut Delay>) -> i16 {
+    /// `vtemp` has to be a right aligned 12 bit sample of channel 16 (e.g.
+    /// acquired via DMA or a sequence) and `vdda` the supply voltage in
+    /// milli-volts at the time of the sample.
+    pub fn convert_temp(vtemp: u16, vdda: u16) -> i16 {
+        Self::scaled_temp(vtemp, vdda, 10) as i16
+    }
+
+    /// Converts a raw temperature sensor sample into milli-degrees centigrade.
+    ///
+    /// Same as [`convert_temp`](VTemp::convert_temp) but with a finer
+    /// fixed-point resolution.
+    pub fn convert_temp_millicelsius(vtemp: u16, vdda: u16) -> i32 {
+        Self::scaled_temp(vtemp, vdda, 1000)
+    }
+
+    fn read_sample(adc: &mut Adc, vdda: Option<u16>, delay: Option<&mut Delay>) -> (u16, u16) {
         let mut vtemp = Self::new();
         let vtemp_preenable = vtemp.is_enabled(adc);
 
@@ -297,7 +487,7 @@ impl VTemp {
                 VRef::read_vdda(adc);
             }
         }
-        let vdda = VRef::read_vdda(adc);
+        let vdda = vdda.unwrap_or_else(|| VRef::read_vdda(adc));
 
         let prev_cfg = adc.default_cfg();
 
@@ -309,7 +499,38 @@ impl VTemp {
 
         adc.restore_cfg(prev_cfg);
 
-        Self::convert_temp(vtemp_val, vdda)
+        (vtemp_val, vdda)
+    }
+
+    /// Read the value of the internal temperature sensor and return the
+    /// result in 10ths of a degree centigrade.
+    ///
+    /// Given a delay reference it will attempt to restrict to the
+    /// minimum delay needed to ensure a 10 us t<sub>START</sub> value.
+    /// Otherwise it will approximate the required delay using ADC reads.
+    pub fn read(adc: &mut Adc, delay: Option<&mut Delay>) -> i16 {
+        let (vtemp, vdda) = Self::read_sample(adc, None, delay);
+        Self::convert_temp(vtemp, vdda)
+    }
+
+    /// Read the value of the internal temperature sensor using a known VDDA
+    /// (in milli-volts) and return the result in 10ths of a degree centigrade.
+    ///
+    /// Useful if VDDA is measured externally or already known, which saves
+    /// the internal reference conversion.
+    pub fn read_with_vdda(adc: &mut Adc, vdda: u16, delay: Option<&mut Delay>) -> i16 {
+        let (vtemp, vdda) = Self::read_sample(adc, Some(vdda), delay);
+        Self::convert_temp(vtemp, vdda)
+    }
+
+    /// Read the value of the internal temperature sensor and return the
+    /// result in milli-degrees centigrade.
+    ///
+    /// If `vdda` (in milli-volts) is `None` it will be measured using the
+    /// internal voltage reference.
+    pub fn read_millicelsius(adc: &mut Adc, vdda: Option<u16>, delay: Option<&mut Delay>) -> i32 {
+        let (vtemp, vdda) = Self::read_sample(adc, vdda, delay);
+        Self::convert_temp_millicelsius(vtemp, vdda)
     }
 }
 
@@ -334,6 +555,12 @@ impl VRef {
         adc.rb.ccr.read().vrefen().bit_is_set()
     }
 
+    /// Enables the internal voltage reference until the returned guard is
+    /// dropped.
+    pub fn enabled(adc: &mut Adc) -> Enabled<'_, Self> {
+        Enabled::new(adc, Self::new())
+    }
+
     /// Reads the value of VDDA in milli-volts
     pub fn read_vdda(adc: &mut Adc) -> u16 {
         let vrefint_cal = u32::from(unsafe { ptr::read(VREFCAL) });
@@ -358,6 +585,71 @@ impl VRef {
     }
 }
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+use self::sealed::Sealed;
+
+/// Internal ADC channel which has to be enabled before use
+pub trait InternalChannel: Channel<Adc, ID = u8> + Sealed {
+    /// Enables the channel
+    fn enable(&mut self, adc: &mut Adc);
+    /// Disables the channel
+    fn disable(&mut self, adc: &mut Adc);
+}
+
+macro_rules! internal_channel {
+    ($($CH:ident),+ $(,)*) => {
+        $(
+            impl Sealed for $CH {}
+
+            impl InternalChannel for $CH {
+                fn enable(&mut self, adc: &mut Adc) {
+                    $CH::enable(self, adc)
+                }
+
+                fn disable(&mut self, adc: &mut Adc) {
+                    $CH::disable(self, adc)
+                }
+            }
+        )+
+    };
+}
+
+internal_channel!(VTemp, VRef);
+
+/// Keeps an internal channel enabled, disabling it again when dropped
+///
+/// Created by `VTemp::enabled`, `VRef::enabled` or `VBat::enabled`.
+pub struct Enabled<'a, CH: InternalChannel> {
+    adc: &'a mut Adc,
+    channel: CH,
+}
+
+impl<'a, CH: InternalChannel> Enabled<'a, CH> {
+    fn new(adc: &'a mut Adc, mut channel: CH) -> Self {
+        channel.enable(adc);
+        Enabled { adc, channel }
+    }
+
+    /// Reads the raw value of the channel
+    pub fn read(&mut self) -> u16 {
+        self.adc.read(&mut self.channel).unwrap()
+    }
+
+    /// Gives access to the ADC while the channel stays enabled
+    pub fn adc(&mut self) -> &mut Adc {
+        self.adc
+    }
+}
+
+impl<'a, CH: InternalChannel> Drop for Enabled<'a, CH> {
+    fn drop(&mut self) {
+        self.channel.disable(self.adc);
+    }
+}
+
 #[cfg(any(
     feature = "stm32f031",
     feature = "stm32f038",
@@ -427,6 +719,11 @@ impl VBat {
         adc.rb.ccr.read().vbaten().bit_is_set()
     }
 
+    /// Enables the internal VBat sense until the returned guard is dropped.
+    pub fn enabled(adc: &mut Adc) -> Enabled<'_, Self> {
+        Enabled::new(adc, Self::new())
+    }
+
     /// Reads the value of VBat in milli-volts
     pub fn read(adc: &mut Adc) -> u16 {
         let mut vbat = Self::new();
@@ -446,9 +743,210 @@ impl VBat {
     }
 }
 
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+internal_channel!(VBat);
+
+/// Highest ADC channel number
+pub const MAX_CHANNEL: u8 = 18;
+
+/// ADC channel selected by number at runtime
+///
+/// Useful for pin-agnostic configuration tables, e.g. when the channel to
+/// sample is read from EEPROM. The caller is responsible for putting the
+/// corresponding GPIO into analog mode (or enabling the internal source for
+/// channels 16-18) before reading.
+///
+/// This type does not implement `embedded_hal::adc::Channel`: its `channel()`
+/// is an associated function without `self`, so the number has to be known
+/// from the type alone and can't be chosen at runtime. Use
+/// [`Adc::read_channel`] instead of `OneShot::read`, or [`AdcChannel::from_pin`]
+/// to get the number of a typed pin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdcChannel(u8);
+
+impl AdcChannel {
+    /// Creates a channel from its number, returns `None` for channels above
+    /// [`MAX_CHANNEL`]
+    pub fn new(channel: u8) -> Option<Self> {
+        if channel <= MAX_CHANNEL {
+            Some(AdcChannel(channel))
+        } else {
+            None
+        }
+    }
+
+    /// Creates the channel matching a statically typed pin or internal source
+    pub fn from_pin<PIN: Channel<Adc, ID = u8>>(_pin: &PIN) -> Self {
+        AdcChannel(PIN::channel())
+    }
+
+    /// Returns the channel number
+    pub fn channel(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Set of channels converted as one sequence
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AdcSequence {
+    channels: u32,
+}
+
+impl AdcSequence {
+    /// Creates an empty sequence
+    pub fn new() -> Self {
+        AdcSequence::default()
+    }
+
+    /// Adds a statically typed pin or internal source to the sequence
+    pub fn channel<PIN: Channel<Adc, ID = u8>>(mut self, _pin: &PIN) -> Self {
+        self.channels |= 1 << PIN::channel();
+        self
+    }
+
+    /// Adds a runtime selected channel to the sequence
+    pub fn channel_number(mut self, channel: AdcChannel) -> Self {
+        self.channels |= 1 << channel.channel();
+        self
+    }
+
+    /// Returns the number of channels in the sequence
+    pub fn len(&self) -> u8 {
+        self.channels.count_ones() as u8
+    }
+
+    /// Returns true if no channel has been added
+    pub fn is_empty(&self) -> bool {
+        self.channels == 0
+    }
+}
+
+/// Result of a sequence conversion, returned by
+/// [Adc::next_sample](crate::adc::Adc::next_sample)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SequenceSample {
+    /// Channel the value was converted from
+    pub channel: u8,
+    /// Conversion result
+    pub value: u16,
+    /// The sample completed the sequence (EOSEQ)
+    pub end_of_sequence: bool,
+}
+
+/// Select lines of an external analog multiplexer
+///
+/// Implemented for tuples of up to four output pins, the first pin being the
+/// least significant select bit.
+pub trait MuxSelect {
+    /// Number of multiplexer inputs which can be selected
+    const INPUTS: u8;
+
+    /// Drives the select lines to `input`
+    fn select(&mut self, input: u8);
+}
+
+macro_rules! mux_select {
+    ($($inputs:expr => ($($S:ident: $bit:tt),+),)+) => {
+        $(
+            impl<$($S),+> MuxSelect for ($($S,)+)
+            where
+                $($S: OutputPin,)+
+            {
+                const INPUTS: u8 = $inputs;
+
+                fn select(&mut self, input: u8) {
+                    $(
+                        if input & (1 << $bit) != 0 {
+                            self.$bit.set_high().ok();
+                        } else {
+                            self.$bit.set_low().ok();
+                        }
+                    )+
+                }
+            }
+        )+
+    };
+}
+
+mux_select! {
+    2 => (S0: 0),
+    4 => (S0: 0, S1: 1),
+    8 => (S0: 0, S1: 1, S2: 2),
+    16 => (S0: 0, S1: 1, S2: 2, S3: 3),
+}
+
+/// ADC channel expanded by an external analog multiplexer (e.g. 74HC4051)
+///
+/// Bundles the analog pin connected to the multiplexer output with the
+/// select lines, and waits for the signal to settle after switching inputs.
+pub struct MuxedChannel<PIN, SEL> {
+    pin: PIN,
+    select: SEL,
+    settling_us: u16,
+    current: Option<u8>,
+}
+
+impl<PIN, SEL> MuxedChannel<PIN, SEL>
+where
+    PIN: Channel<Adc, ID = u8>,
+    SEL: MuxSelect,
+{
+    /// Creates a muxed channel waiting `settling_us` after switching inputs
+    pub fn new(pin: PIN, select: SEL, settling_us: u16) -> Self {
+        MuxedChannel {
+            pin,
+            select,
+            settling_us,
+            current: None,
+        }
+    }
+
+    /// Number of multiplexer inputs
+    pub fn inputs(&self) -> u8 {
+        SEL::INPUTS
+    }
+
+    /// Selects `input` of the multiplexer and reads it
+    ///
+    /// The settling delay is only applied if the input changed since the last
+    /// read. Panics if `input` is not a valid multiplexer input.
+    pub fn read<D: DelayUs<u16>>(&mut self, adc: &mut Adc, input: u8, delay: &mut D) -> u16 {
+        assert!(input < SEL::INPUTS);
+        if self.current != Some(input) {
+            self.select.select(input);
+            self.current = Some(input);
+            delay.delay_us(self.settling_us);
+        }
+        adc.read_raw(PIN::channel())
+    }
+
+    /// Releases the analog pin and the select lines
+    pub fn release(self) -> (PIN, SEL) {
+        (self.pin, self.select)
+    }
+}
+
 /// A stored ADC config, can be restored by using the `Adc::restore_cfg` method
 #[derive(Copy, Clone, Debug, PartialEq)]
-pub struct StoredConfig(AdcSampleTime, AdcAlign, AdcPrecision);
+pub struct StoredConfig(
+    AdcSampleTime,
+    AdcAlign,
+    AdcPrecision,
+    AdcTriggerEdge,
+    AdcOverrunMode,
+);
 
 impl Adc {
     /// Init a new Adc
@@ -457,20 +955,41 @@ impl Adc {
     /// for the ADC if it is not already enabled and performs a boot time
     /// calibration. As such this method may take an appreciable time to run.
     pub fn new(adc: ADC, rcc: &mut Rcc) -> Self {
+        Self::new_with_config(adc, rcc, AdcConfig::default())
+    }
+
+    /// Init a new Adc with the given configuration
+    ///
+    /// Like [new](Adc::new), but applies `config` before the boot time
+    /// calibration. The HSI14 clock is only enabled if it is selected as
+    /// clock source.
+    pub fn new_with_config(adc: ADC, rcc: &mut Rcc, config: AdcConfig) -> Self {
         let mut s = Self {
             rb: adc,
-            sample_time: AdcSampleTime::default(),
-            align: AdcAlign::default(),
-            precision: AdcPrecision::default(),
+            sample_time: config.sample_time,
+            align: config.align,
+            precision: config.precision,
+            trigger_edge: config.trigger_edge,
+            overrun_mode: config.overrun_mode,
+            sequence: 0,
+            sequence_pos: 0,
+            vdda_refresh: 0,
+            vdda_cache: None,
         };
-        s.select_clock(rcc);
+        s.select_clock(rcc, config.clock_mode);
         s.calibrate();
         s
     }
 
     /// Saves a copy of the current ADC config
     pub fn save_cfg(&mut self) -> StoredConfig {
-        StoredConfig(self.sample_time, self.align, self.precision)
+        StoredConfig(
+            self.sample_time,
+            self.align,
+            self.precision,
+            self.trigger_edge,
+            self.overrun_mode,
+        )
     }
 
     /// Restores a stored config
@@ -478,6 +997,8 @@ impl Adc {
         self.sample_time = cfg.0;
         self.align = cfg.1;
         self.precision = cfg.2;
+        self.trigger_edge = cfg.3;
+        self.overrun_mode = cfg.4;
     }
 
     /// Resets the ADC config to default, returning the existing config as
@@ -487,6 +1008,8 @@ impl Adc {
         self.sample_time = AdcSampleTime::default();
         self.align = AdcAlign::default();
         self.precision = AdcPrecision::default();
+        self.trigger_edge = AdcTriggerEdge::default();
+        self.overrun_mode = AdcOverrunMode::default();
         cfg
     }
 
@@ -511,6 +1034,23 @@ impl Adc {
         self.precision = precision;
     }
 
+    /// Set the edge of the external trigger signal starting a conversion
+    ///
+    /// Options can be found in [AdcTriggerEdge](crate::adc::AdcTriggerEdge).
+    /// Only applies to hardware triggered conversions.
+    pub fn set_trigger_edge(&mut self, edge: AdcTriggerEdge) {
+        self.trigger_edge = edge;
+    }
+
+    /// Set whether an overrun keeps or overwrites the unread result
+    ///
+    /// Options can be found in [AdcOverrunMode](crate::adc::AdcOverrunMode).
+    /// Overruns are reported as [Error::Overrun](crate::adc::Error::Overrun)
+    /// by the sequence APIs in either mode.
+    pub fn set_overrun_mode(&mut self, mode: AdcOverrunMode) {
+        self.overrun_mode = mode;
+    }
+
     /// Returns the largest possible sample value for the current settings
     pub fn max_sample(&self) -> u16 {
         match self.align {
@@ -528,15 +1068,288 @@ impl Adc {
         }
     }
 
+    /// Set how many `read_abs_mv` calls reuse a VDDA measurement
+    ///
+    /// With the default of 0 VDDA is measured for every reading. Otherwise the
+    /// measurement is repeated after `reads` readings, halving the time per
+    /// reading at the cost of following supply changes more slowly.
+    pub fn set_vdda_refresh(&mut self, reads: u16) {
+        self.vdda_refresh = reads;
+        self.vdda_cache = None;
+    }
+
+    /// Discards the cached VDDA, the next `read_abs_mv` measures it again
+    pub fn invalidate_vdda(&mut self) {
+        self.vdda_cache = None;
+    }
+
+    /// Returns VDDA in milli-volts, measured or cached as set by
+    /// [set_vdda_refresh](Adc::set_vdda_refresh)
+    fn vdda(&mut self) -> u16 {
+        match self.vdda_cache {
+            Some((vdda, age)) if age < self.vdda_refresh => {
+                self.vdda_cache = Some((vdda, age + 1));
+                vdda
+            }
+            _ => {
+                let vdda = VRef::read_vdda(self);
+                if self.vdda_refresh > 0 {
+                    self.vdda_cache = Some((vdda, 1));
+                }
+                vdda
+            }
+        }
+    }
+
     /// Read the value of a channel and converts the result to milli-volts
     pub fn read_abs_mv<PIN: Channel<Adc, ID = u8>>(&mut self, pin: &mut PIN) -> u16 {
-        let vdda = u32::from(VRef::read_vdda(self));
+        let vdda = u32::from(self.vdda());
         let v: u32 = self.read(pin).unwrap();
         let max_samp = u32::from(self.max_sample());
 
         (v * vdda / max_samp) as u16
     }
 
+    /// Read the value of a channel selected at runtime
+    pub fn read_channel(&mut self, channel: AdcChannel) -> u16 {
+        self.read_raw(channel.channel())
+    }
+
+    /// Read the value of a channel by its number
+    ///
+    /// This is the conversion path used by all single reads: it selects
+    /// `channel` as the only channel in CHSELR, applies the current sample
+    /// time, precision and alignment settings and performs one software
+    /// triggered conversion. Results are corrected the same way as for the
+    /// other read functions, in particular 6 bit left aligned values are
+    /// shifted into the upper byte unless
+    /// [AdcAlign::LeftAsRM](crate::adc::AdcAlign::LeftAsRM) is selected.
+    ///
+    /// CHSELR is left selecting `channel` afterwards.
+    ///
+    /// Panics if `channel` is larger than [MAX_CHANNEL](crate::adc::MAX_CHANNEL).
+    pub fn read_raw(&mut self, channel: u8) -> u16 {
+        assert!(channel <= MAX_CHANNEL);
+        self.power_up();
+        let res = self.convert(channel);
+        self.power_down();
+        res
+    }
+
+    /// Read a channel with software oversampling
+    ///
+    /// Accumulates `2^ratio` conversions and shifts the sum right by
+    /// `ratio / 2`, which gains one bit of resolution per factor of four
+    /// samples for signals with enough noise (or dither) and a rate well
+    /// below the conversion time. With the default 12 bit right aligned
+    /// configuration the result has `12 + ratio / 2` significant bits.
+    ///
+    /// `ratio` must not be larger than 16.
+    pub fn read_oversampled<PIN: Channel<Adc, ID = u8>>(
+        &mut self,
+        _pin: &mut PIN,
+        ratio: u8,
+    ) -> u32 {
+        assert!(ratio <= 16);
+
+        let mut reader = self.reader();
+        let sum = (0..1_u32 << ratio).fold(0_u32, |sum, _| {
+            sum + u32::from(reader.read_raw(PIN::channel()))
+        });
+        drop(reader);
+
+        sum >> (ratio / 2)
+    }
+
+    /// Capture an interleaved burst from two channels sampling the same signal
+    ///
+    /// The F0 only has a single ADC, but the effective sample rate of a burst
+    /// can be doubled by feeding one signal into two channels through external
+    /// sample & hold stages (or an external analog multiplexer) whose hold
+    /// strobes are driven half a period apart by timer compare outputs. Each
+    /// `trigger` event (usually the TRGO of the same timer, configured to fire
+    /// once both values are held) converts both channels back to back.
+    ///
+    /// The results are stored time-ordered in `buf`: samples of `first` end up
+    /// at the even, samples of `second` at the odd indices. The function
+    /// blocks until `buf` is filled; if `buf` has an odd length the last slot
+    /// only receives a sample of `first`.
+    ///
+    /// Returns [Error::Overrun](crate::adc::Error::Overrun) and stops the
+    /// burst if a result was overwritten before it could be read.
+    pub fn read_interleaved<A, B>(
+        &mut self,
+        _first: &mut A,
+        _second: &mut B,
+        trigger: AdcTrigger,
+        buf: &mut [u16],
+    ) -> Result<(), Error>
+    where
+        A: Channel<Adc, ID = u8>,
+        B: Channel<Adc, ID = u8>,
+    {
+        let (chan_a, chan_b) = (A::channel(), B::channel());
+        assert!(chan_a != chan_b);
+
+        self.power_up();
+
+        self.rb
+            .chselr
+            .write(|w| unsafe { w.bits((1_u32 << chan_a) | (1_u32 << chan_b)) });
+        self.configure_conversion();
+
+        // Convert the whole two channel sequence on every trigger event
+        self.rb
+            .cfgr1
+            .modify(|_, w| w.cont().clear_bit().discen().clear_bit());
+        self.set_trigger(Some(trigger));
+
+        // The sequencer always scans upwards, so the lower channel is converted first
+        let a_first = chan_a < chan_b;
+
+        self.clear_overrun();
+        self.rb.cr.modify(|_, w| w.adstart().start_conversion());
+
+        let res = self.fill_interleaved(a_first, buf);
+
+        self.power_down();
+
+        // Back to software triggered conversions
+        self.set_trigger(None);
+
+        res
+    }
+
+    fn fill_interleaved(&mut self, a_first: bool, buf: &mut [u16]) -> Result<(), Error> {
+        for pair in buf.chunks_mut(2) {
+            let first = self.wait_result();
+            let second = self.wait_result();
+            self.check_overrun()?;
+            let (a, b) = if a_first {
+                (first, second)
+            } else {
+                (second, first)
+            };
+
+            pair[0] = a;
+            if let Some(slot) = pair.get_mut(1) {
+                *slot = b;
+            }
+        }
+        Ok(())
+    }
+
+    /// Arms hardware triggered conversions of a channel sequence
+    ///
+    /// The channels of `sequence` are always converted in ascending order.
+    /// Normally every `trigger` event converts the whole sequence. In
+    /// `discontinuous` mode each event only converts the next channel of the
+    /// sequence, wrapping around after the last one, e.g. to sample exactly one
+    /// channel of a rotating set per PWM period.
+    ///
+    /// Results have to be fetched with
+    /// [`sequence_result`](Adc::sequence_result) before the next conversion
+    /// completes. Call [`stop_sequence`](Adc::stop_sequence) before using the
+    /// ADC for other conversions again.
+    pub fn start_sequence(
+        &mut self,
+        sequence: AdcSequence,
+        trigger: AdcTrigger,
+        discontinuous: bool,
+    ) {
+        self.power_up();
+
+        self.rb
+            .chselr
+            .write(|w| unsafe { w.bits(sequence.channels) });
+        self.configure_conversion();
+
+        self.rb
+            .cfgr1
+            .modify(|_, w| w.cont().clear_bit().discen().bit(discontinuous));
+        self.set_trigger(Some(trigger));
+
+        self.sequence = sequence.channels;
+        self.sequence_pos = 0;
+        self.rb.isr.write(|w| w.eoseq().set_bit());
+
+        self.clear_overrun();
+        self.rb.cr.modify(|_, w| w.adstart().start_conversion());
+    }
+
+    /// Returns the result of the last conversion of a running sequence
+    ///
+    /// Returns [Error::Overrun](crate::adc::Error::Overrun) once if results
+    /// were lost since the last call. Depending on the
+    /// [overrun mode](Adc::set_overrun_mode) the next result is then either
+    /// the oldest or the newest unread conversion.
+    pub fn sequence_result(&mut self) -> nb::Result<u16, Error> {
+        self.check_overrun()?;
+        if self.rb.isr.read().eoc().is_not_complete() {
+            Err(nb::Error::WouldBlock)
+        } else {
+            Ok(self.read_result())
+        }
+    }
+
+    /// Returns the next result of a running sequence with its channel
+    ///
+    /// Unlike [`sequence_result`](Adc::sequence_result) this tells which channel
+    /// a value belongs to and whether it completed the sequence (EOSEQ). Each
+    /// result has to be fetched before the next conversion completes, as the
+    /// data register only holds one value. After an overrun the channel
+    /// numbering resynchronizes at the end of the sequence.
+    pub fn next_sample(&mut self) -> nb::Result<SequenceSample, Error> {
+        if let Err(e) = self.check_overrun() {
+            self.sequence_pos = 0;
+            return Err(nb::Error::Other(e));
+        }
+        if self.rb.isr.read().eoc().is_not_complete() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let value = self.read_result();
+        let end_of_sequence = self.rb.isr.read().eoseq().bit_is_set();
+        let channel = if end_of_sequence {
+            // EOSEQ is cleared by writing 1
+            self.rb.isr.write(|w| w.eoseq().set_bit());
+            self.sequence_pos = 0;
+            (31 - self.sequence.leading_zeros()) as u8
+        } else {
+            let channel = nth_channel(self.sequence, self.sequence_pos);
+            self.sequence_pos += 1;
+            channel
+        };
+
+        Ok(SequenceSample {
+            channel,
+            value,
+            end_of_sequence,
+        })
+    }
+
+    /// Stops a sequence started by [`start_sequence`](Adc::start_sequence)
+    pub fn stop_sequence(&mut self) {
+        self.power_down();
+        self.rb.cfgr1.modify(|_, w| w.discen().clear_bit());
+        self.set_trigger(None);
+    }
+
+    fn set_trigger(&mut self, trigger: Option<AdcTrigger>) {
+        self.rb.cfgr1.modify(|r, w| {
+            let bits = r.bits() & !(CFGR1_EXTSEL_MASK | CFGR1_EXTEN_MASK);
+            let bits = match trigger {
+                Some(t) => {
+                    bits | (t.extsel_bits() << CFGR1_EXTSEL_SHIFT)
+                        | (self.trigger_edge.exten_bits() << CFGR1_EXTEN_SHIFT)
+                }
+                // Software trigger
+                None => bits,
+            };
+            unsafe { w.bits(bits) }
+        });
+    }
+
     fn calibrate(&mut self) {
         /* Ensure that ADEN = 0 */
         if self.rb.cr.read().aden().is_enabled() {
@@ -555,10 +1368,26 @@ impl Adc {
         while self.rb.cr.read().adcal().is_calibrating() {}
     }
 
-    fn select_clock(&mut self, rcc: &mut Rcc) {
+    fn select_clock(&mut self, rcc: &mut Rcc, mode: AdcClockMode) {
         rcc.regs.apb2enr.modify(|_, w| w.adcen().enabled());
-        rcc.regs.cr2.modify(|_, w| w.hsi14on().on());
-        while rcc.regs.cr2.read().hsi14rdy().is_not_ready() {}
+        clock_event!(enabled, "ADC");
+        if mode == AdcClockMode::PclkDiv2 {
+            assert!(
+                rcc.clocks.pclk() == rcc.clocks.hclk(),
+                "PCLK/2 ADC clock needs an APB prescaler of 1"
+            );
+        }
+        if mode == AdcClockMode::Hsi14 {
+            rcc.regs.cr2.modify(|_, w| w.hsi14on().on());
+            while rcc.regs.cr2.read().hsi14rdy().is_not_ready() {}
+        }
+        /* CKMODE can only be written while ADEN = 0 */
+        if self.rb.cr.read().aden().is_enabled() {
+            self.power_down();
+        }
+        self.rb
+            .cfgr2
+            .write(|w| unsafe { w.bits(mode.ckmode_bits() << CFGR2_CKMODE_SHIFT) });
     }
 
     fn power_up(&mut self) {
@@ -576,9 +1405,7 @@ impl Adc {
         while self.rb.cr.read().aden().is_enabled() {}
     }
 
-    fn convert(&mut self, chan: u8) -> u16 {
-        self.rb.chselr.write(|w| unsafe { w.bits(1_u32 << chan) });
-
+    fn configure_conversion(&mut self) {
         self.rb
             .smpr
             .write(|w| w.smp().variant(self.sample_time.into()));
@@ -587,11 +1414,31 @@ impl Adc {
                 .variant(self.precision.into())
                 .align()
                 .variant(self.align.into())
+                .ovrmod()
+                .bit(self.overrun_mode == AdcOverrunMode::Overwrite)
         });
+    }
 
-        self.rb.cr.modify(|_, w| w.adstart().start_conversion());
+    fn clear_overrun(&mut self) {
+        // OVR is cleared by writing 1
+        self.rb.isr.write(|w| w.ovr().set_bit());
+    }
+
+    fn check_overrun(&mut self) -> Result<(), Error> {
+        if self.rb.isr.read().ovr().bit_is_set() {
+            self.clear_overrun();
+            Err(Error::Overrun)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn wait_result(&mut self) -> u16 {
         while self.rb.isr.read().eoc().is_not_complete() {}
+        self.read_result()
+    }
 
+    fn read_result(&mut self) -> u16 {
         let res = self.rb.dr.read().bits() as u16;
         if self.align == AdcAlign::Left && self.precision == AdcPrecision::B_6 {
             res << 8
@@ -599,6 +1446,79 @@ impl Adc {
             res
         }
     }
+
+    fn convert(&mut self, chan: u8) -> u16 {
+        self.configure_conversion();
+        self.convert_configured(chan)
+    }
+
+    /// Converts `chan` with the settings already in CFGR1 and SMPR
+    fn convert_configured(&mut self, chan: u8) -> u16 {
+        self.rb.chselr.write(|w| unsafe { w.bits(1_u32 << chan) });
+        self.rb.cr.modify(|_, w| w.adstart().start_conversion());
+        self.wait_result()
+    }
+
+    /// Powers up the ADC and applies the current settings once for a series
+    /// of fast single reads
+    ///
+    /// The ADC stays powered until the returned reader is dropped. Changing
+    /// the settings requires dropping the reader first.
+    pub fn reader(&mut self) -> AdcReader<'_> {
+        self.power_up();
+        self.configure_conversion();
+        AdcReader { adc: self }
+    }
+}
+
+/// Handle for fast single reads, created by [Adc::reader](crate::adc::Adc::reader)
+///
+/// Reads only select the channel and start the conversion, the sample time,
+/// precision and alignment are configured once when the reader is created.
+pub struct AdcReader<'a> {
+    adc: &'a mut Adc,
+}
+
+impl<'a> AdcReader<'a> {
+    /// Read the value of a channel by its number
+    ///
+    /// Panics if `channel` is larger than [MAX_CHANNEL](crate::adc::MAX_CHANNEL).
+    pub fn read_raw(&mut self, channel: u8) -> u16 {
+        assert!(channel <= MAX_CHANNEL);
+        self.adc.convert_configured(channel)
+    }
+
+    /// Read the value of a channel selected at runtime
+    pub fn read_channel(&mut self, channel: AdcChannel) -> u16 {
+        self.adc.convert_configured(channel.channel())
+    }
+}
+
+impl<'a> Drop for AdcReader<'a> {
+    fn drop(&mut self) {
+        self.adc.power_down();
+    }
+}
+
+impl<'a, WORD, PIN> OneShot<Adc, WORD, PIN> for AdcReader<'a>
+where
+    WORD: From<u16>,
+    PIN: Channel<Adc, ID = u8>,
+{
+    type Error = ();
+
+    fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
+        Ok(self.adc.convert_configured(PIN::channel()).into())
+    }
+}
+
+/// Returns the channel number of the `n`th set bit of `channels`
+fn nth_channel(channels: u32, n: u8) -> u8 {
+    let mut channels = channels;
+    for _ in 0..n {
+        channels &= channels.wrapping_sub(1);
+    }
+    channels.trailing_zeros() as u8
 }
 
 impl<WORD, PIN> OneShot<Adc, WORD, PIN> for Adc
@@ -609,9 +1529,6 @@ where
     type Error = ();
 
     fn read(&mut self, _pin: &mut PIN) -> nb::Result<WORD, Self::Error> {
-        self.power_up();
-        let res = self.convert(PIN::channel());
-        self.power_down();
-        Ok(res.into())
+        Ok(self.read_raw(PIN::channel()).into())
     }
 }
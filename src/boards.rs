@@ -0,0 +1,134 @@
+//! Pin assignments of common ST development boards
+//!
+//! Each board is enabled with its own feature, which also selects the
+//! matching device:
+//!
+//! - `board-nucleo-f042k6`: NUCLEO-F042K6
+//! - `board-stm32f072b-disco`: 32F072BDISCOVERY
+//!
+//! The constructors only configure pins and peripherals already provided by
+//! the HAL, they are meant as a starting point and to look up the wiring.
+//!
+//! # Example
+//! ``` no_run
+//! use stm32f0xx_hal as hal;
+//!
+//! use crate::hal::boards::nucleo_f042k6;
+//! use crate::hal::pac;
+//! use crate::hal::prelude::*;
+//!
+//! cortex_m::interrupt::free(|cs| {
+//!     let mut p = pac::Peripherals::take().unwrap();
+//!     let mut rcc = p.RCC.configure().sysclk(48.mhz()).freeze(&mut p.FLASH);
+//!
+//!     let gpioa = p.GPIOA.split(&mut rcc);
+//!     let gpiob = p.GPIOB.split(&mut rcc);
+//!
+//!     let mut led = nucleo_f042k6::led(gpiob.pb3, cs);
+//!     let mut vcp = nucleo_f042k6::vcp(p.USART2, gpioa.pa2, gpioa.pa15, 115_200.bps(), &mut rcc, cs);
+//!
+//!     led.set_high().ok();
+//! });
+//! ```
+
+/// NUCLEO-F042K6
+///
+/// The user LED LD3 is on PB3, the virtual COM port of the ST-LINK is wired
+/// to USART2 on PA2 (TX) and PA15 (RX). The board has no user button.
+#[cfg(feature = "board-nucleo-f042k6")]
+pub mod nucleo_f042k6 {
+    use cortex_m::interrupt::CriticalSection;
+
+    use crate::gpio::gpioa::{PA15, PA2};
+    use crate::gpio::gpiob::PB3;
+    use crate::gpio::{Alternate, Output, PushPull, AF1};
+    use crate::pac::USART2;
+    use crate::rcc::Rcc;
+    use crate::serial::Serial;
+    use crate::time::Bps;
+
+    /// User LED LD3, active high
+    pub type Led = PB3<Output<PushPull>>;
+
+    /// USART connected to the virtual COM port of the ST-LINK
+    pub type Vcp = Serial<USART2, PA2<Alternate<AF1>>, PA15<Alternate<AF1>>>;
+
+    /// Configures the user LED
+    pub fn led<MODE>(pin: PB3<MODE>, cs: &CriticalSection) -> Led {
+        pin.into_push_pull_output(cs)
+    }
+
+    /// Configures the virtual COM port with `baud_rate`, 8N1
+    pub fn vcp<TXMODE, RXMODE>(
+        usart: USART2,
+        tx: PA2<TXMODE>,
+        rx: PA15<RXMODE>,
+        baud_rate: Bps,
+        rcc: &mut Rcc,
+        cs: &CriticalSection,
+    ) -> Vcp {
+        let pins = (tx.into_alternate_af1(cs), rx.into_alternate_af1(cs));
+        Serial::usart2(usart, pins, baud_rate, rcc)
+    }
+}
+
+/// 32F072BDISCOVERY
+///
+/// The four user LEDs are on PC6 to PC9, the user button B1 on PA0 is active
+/// high with an external pull-down. The ST-LINK of this board has no virtual
+/// COM port.
+#[cfg(feature = "board-stm32f072b-disco")]
+pub mod stm32f072b_disco {
+    use cortex_m::interrupt::CriticalSection;
+
+    use crate::gpio::gpioa::PA0;
+    use crate::gpio::gpioc::{PC6, PC7, PC8, PC9};
+    use crate::gpio::{Floating, Input, Output, PushPull};
+    use crate::pac::{EXTI, SYSCFG};
+    use crate::rcc::Rcc;
+
+    /// User LEDs, active high
+    pub struct Leds {
+        /// LD3, red, top
+        pub up: PC6<Output<PushPull>>,
+        /// LD6, blue, bottom
+        pub down: PC7<Output<PushPull>>,
+        /// LD4, orange, left
+        pub left: PC8<Output<PushPull>>,
+        /// LD5, green, right
+        pub right: PC9<Output<PushPull>>,
+    }
+
+    /// User button B1, reads high while pressed
+    pub type Button = PA0<Input<Floating>>;
+
+    /// Configures the user LEDs
+    pub fn leds<M6, M7, M8, M9>(
+        pins: (PC6<M6>, PC7<M7>, PC8<M8>, PC9<M9>),
+        cs: &CriticalSection,
+    ) -> Leds {
+        Leds {
+            up: pins.0.into_push_pull_output(cs),
+            down: pins.1.into_push_pull_output(cs),
+            left: pins.2.into_push_pull_output(cs),
+            right: pins.3.into_push_pull_output(cs),
+        }
+    }
+
+    /// Configures the user button, the board provides the pull-down
+    pub fn button<MODE>(pin: PA0<MODE>, cs: &CriticalSection) -> Button {
+        pin.into_floating_input(cs)
+    }
+
+    /// Raises EXTI line 0 (interrupt `EXTI0_1`) when the button is pressed
+    ///
+    /// The pending bit has to be cleared in the handler by writing 1 to
+    /// `EXTI.PR.PR0`.
+    pub fn listen_button(_button: &Button, syscfg: &SYSCFG, exti: &EXTI, rcc: &mut Rcc) {
+        rcc.regs.apb2enr.modify(|_, w| w.syscfgen().set_bit());
+        // Route PA0 to line 0
+        syscfg.exticr1.modify(|_, w| unsafe { w.exti0().bits(0) });
+        exti.rtsr.modify(|_, w| w.tr0().set_bit());
+        exti.imr.modify(|_, w| w.mr0().set_bit());
+    }
+}
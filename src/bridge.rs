@@ -0,0 +1,198 @@
+//! Transparent byte bridge between two serial endpoints
+//!
+//! [Bridge] forwards everything received on one endpoint to the other one and
+//! vice versa, e.g. between two USARTs or between a USART and the CDC class of
+//! `usbd-serial`. Any type implementing the non-blocking `embedded_hal`
+//! serial traits can be used as endpoint.
+//!
+//! # Example
+//! ``` no_run
+//! use stm32f0xx_hal as hal;
+//!
+//! use crate::hal::bridge::Bridge;
+//! use crate::hal::pac;
+//! use crate::hal::prelude::*;
+//! use crate::hal::serial::Serial;
+//!
+//! cortex_m::interrupt::free(|cs| {
+//!     let mut p = pac::Peripherals::take().unwrap();
+//!     let mut rcc = p.RCC.configure().sysclk(48.mhz()).freeze(&mut p.FLASH);
+//!
+//!     let gpioa = p.GPIOA.split(&mut rcc);
+//!     let usart1 = (gpioa.pa9.into_alternate_af1(cs), gpioa.pa10.into_alternate_af1(cs));
+//!     let usart2 = (gpioa.pa2.into_alternate_af1(cs), gpioa.pa3.into_alternate_af1(cs));
+//!
+//!     let a = Serial::usart1(p.USART1, usart1, 115_200.bps(), &mut rcc);
+//!     let b = Serial::usart2(p.USART2, usart2, 115_200.bps(), &mut rcc);
+//!
+//!     let mut bridge: Bridge<_, _, 64> = Bridge::new(a, b);
+//!     loop {
+//!         bridge.poll();
+//!     }
+//! });
+//! ```
+
+use embedded_hal::serial::{Read, Write};
+
+/// Byte queue of a forwarding direction
+struct Queue<const N: usize> {
+    buf: [u8; N],
+    start: usize,
+    len: usize,
+}
+
+impl<const N: usize> Queue<N> {
+    const fn new() -> Self {
+        Queue {
+            buf: [0; N],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.buf[(self.start + self.len) % N] = byte;
+        self.len += 1;
+    }
+
+    fn front(&self) -> Option<u8> {
+        if self.len > 0 {
+            Some(self.buf[self.start])
+        } else {
+            None
+        }
+    }
+
+    fn pop(&mut self) {
+        self.start = (self.start + 1) % N;
+        self.len -= 1;
+    }
+}
+
+/// Counters of a forwarding direction
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Bytes written to the receiving endpoint
+    pub forwarded: u32,
+    /// Read errors of the sending endpoint, e.g. overruns or framing errors
+    pub read_errors: u32,
+    /// Write errors of the receiving endpoint, the byte is dropped
+    pub write_errors: u32,
+}
+
+/// Buffered bidirectional forwarding between the endpoints `A` and `B`
+///
+/// Each direction buffers up to `N` bytes. While a buffer is full no more
+/// bytes are read from its sender, so a slow receiver throttles the sender
+/// as far as its flow control allows, e.g. USB CDC, and otherwise the
+/// sender's receive overrun shows up in the statistics.
+pub struct Bridge<A, B, const N: usize> {
+    a: A,
+    b: B,
+    a_to_b: Queue<N>,
+    b_to_a: Queue<N>,
+    a_stats: Stats,
+    b_stats: Stats,
+}
+
+impl<A, B, const N: usize> Bridge<A, B, N>
+where
+    A: Read<u8> + Write<u8>,
+    B: Read<u8> + Write<u8>,
+{
+    /// Creates a bridge between `a` and `b`
+    pub fn new(a: A, b: B) -> Self {
+        assert!(N > 0);
+        Bridge {
+            a,
+            b,
+            a_to_b: Queue::new(),
+            b_to_a: Queue::new(),
+            a_stats: Stats::default(),
+            b_stats: Stats::default(),
+        }
+    }
+
+    /// Moves all bytes that can be transferred without blocking
+    ///
+    /// Call this from the main loop, or from the interrupts of both endpoints
+    /// and the USB interrupt. Returns true if any byte was moved.
+    pub fn poll(&mut self) -> bool {
+        let a_to_b = pump(
+            &mut self.a,
+            &mut self.b,
+            &mut self.a_to_b,
+            &mut self.a_stats,
+        );
+        let b_to_a = pump(
+            &mut self.b,
+            &mut self.a,
+            &mut self.b_to_a,
+            &mut self.b_stats,
+        );
+        a_to_b || b_to_a
+    }
+
+    /// Returns the counters of the direction from `A` to `B`
+    pub fn a_to_b_stats(&self) -> Stats {
+        self.a_stats
+    }
+
+    /// Returns the counters of the direction from `B` to `A`
+    pub fn b_to_a_stats(&self) -> Stats {
+        self.b_stats
+    }
+
+    /// Gives access to the endpoints, e.g. to poll the USB device
+    pub fn endpoints(&mut self) -> (&mut A, &mut B) {
+        (&mut self.a, &mut self.b)
+    }
+
+    /// Stops the bridge and returns the endpoints, buffered bytes are lost
+    pub fn release(self) -> (A, B) {
+        (self.a, self.b)
+    }
+}
+
+/// Forwards bytes from `from` to `to` through `queue`
+fn pump<F, T, const N: usize>(
+    from: &mut F,
+    to: &mut T,
+    queue: &mut Queue<N>,
+    stats: &mut Stats,
+) -> bool
+where
+    F: Read<u8>,
+    T: Write<u8>,
+{
+    let mut moved = false;
+
+    while !queue.is_full() {
+        match from.read() {
+            Ok(byte) => queue.push(byte),
+            Err(nb::Error::WouldBlock) => break,
+            Err(nb::Error::Other(_)) => stats.read_errors = stats.read_errors.wrapping_add(1),
+        }
+    }
+
+    while let Some(byte) = queue.front() {
+        match to.write(byte) {
+            Ok(()) => {
+                queue.pop();
+                stats.forwarded = stats.forwarded.wrapping_add(1);
+                moved = true;
+            }
+            Err(nb::Error::WouldBlock) => break,
+            Err(nb::Error::Other(_)) => {
+                queue.pop();
+                stats.write_errors = stats.write_errors.wrapping_add(1);
+            }
+        }
+    }
+
+    moved
+}
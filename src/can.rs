@@ -14,7 +14,17 @@ use self::sealed::Sealed;
 
 pub use bxcan;
 
+/// Pin usable as CAN RX
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as CAN RX",
+    note = "CAN RX is available on PA11 and PB8 (AF4) or PD0 (AF0)"
+)]
 pub trait RxPin: Sealed {}
+/// Pin usable as CAN TX
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as CAN TX",
+    note = "CAN TX is available on PA12 and PB9 (AF4) or PD1 (AF0)"
+)]
 pub trait TxPin: Sealed {}
 
 macro_rules! can_pins {
@@ -61,6 +71,7 @@ pub struct CanInstance<T: TxPin, R: RxPin> {
 impl<T: TxPin, R: RxPin> CanInstance<T, R> {
     pub fn new(peripheral: CAN, tx: T, rx: R, rcc: &mut Rcc) -> Self {
         rcc.regs.apb1enr.modify(|_, w| w.canen().enabled());
+        clock_event!(enabled, "CAN");
         rcc.regs.apb1rstr.modify(|_, w| w.canrst().reset());
         rcc.regs.apb1rstr.modify(|_, w| w.canrst().clear_bit());
 
@@ -0,0 +1,353 @@
+//! API for input capture on the timers
+//!
+//! The counter of the timer runs freely at a configurable tick rate, each
+//! capture channel latches the counter value on the selected edge of its pin.
+//!
+//! # Example
+//! Measure the period of a signal on PA6
+//! ``` no_run
+//! use stm32f0xx_hal as hal;
+//!
+//! use crate::hal::pac;
+//! use crate::hal::prelude::*;
+//! use crate::hal::capture::{Capture, CaptureConfig};
+//! use nb::block;
+//!
+//! cortex_m::interrupt::free(|cs| {
+//!     let mut p = pac::Peripherals::take().unwrap();
+//!     let mut rcc = p.RCC.configure().sysclk(48.mhz()).freeze(&mut p.FLASH);
+//!
+//!     let gpioa = p.GPIOA.split(&mut rcc);
+//!     let pin = gpioa.pa6.into_alternate_af1(cs);
+//!
+//!     // Count with 1MHz
+//!     let mut capture = Capture::tim3(p.TIM3, &mut rcc, 1.mhz());
+//!     let mut ch1 = capture.channel1(pin, CaptureConfig::default());
+//!
+//!     let mut last = block!(ch1.capture()).unwrap();
+//!     loop {
+//!         let now = block!(ch1.capture()).unwrap();
+//!         let _period_us = now.wrapping_sub(last);
+//!         last = now;
+//!     }
+//! });
+//! ```
+
+use core::marker::PhantomData;
+
+use crate::pwm::{C1, C2, C3, C4};
+use crate::rcc::{Clocks, Rcc};
+use crate::time::Hertz;
+use crate::timers::{PinC1, PinC2, PinC3, PinC4};
+
+/// Input capture error
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum Error {
+    /// A capture was overwritten before it could be read
+    Overcapture,
+}
+
+/// Edge of a timer input signal triggering a capture or a timer event
+///
+/// Also re-exported as `timers::Edge` for the trigger inputs of the timers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    /// Capture on rising edges
+    Rising,
+    /// Capture on falling edges
+    Falling,
+    /// Capture on both edges
+    Both,
+}
+
+/// Number of edges per capture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Prescaler {
+    /// Capture on every edge
+    Div1,
+    /// Capture on every second edge
+    Div2,
+    /// Capture on every fourth edge
+    Div4,
+    /// Capture on every eighth edge
+    Div8,
+}
+
+/// Capture channel configuration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CaptureConfig {
+    /// Triggering edge
+    pub edge: Edge,
+    /// Input prescaler
+    pub prescaler: Prescaler,
+    /// Digital input filter (ICxF), `0` disables filtering and `15` is the
+    /// strongest filter
+    pub filter: u8,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            edge: Edge::Rising,
+            prescaler: Prescaler::Div1,
+            filter: 0,
+        }
+    }
+}
+
+/// Timer in input capture mode
+pub struct Capture<TIM> {
+    clocks: Clocks,
+    tim: TIM,
+}
+
+/// Single input capture channel
+pub struct CaptureChannel<TIM, CHANNEL> {
+    _channel: PhantomData<CHANNEL>,
+    _tim: PhantomData<TIM>,
+}
+
+/// Source of captured counter values, implemented by every [CaptureChannel]
+pub trait CaptureInput {
+    /// Returns the latest captured counter value, see [CaptureChannel]
+    fn capture(&mut self) -> nb::Result<u16, Error>;
+}
+
+// CCMRx fields of the first channel of the register, the second one is
+// shifted by 8
+const CCMR_CCS_INPUT: u32 = 0b01;
+const CCMR_ICPSC_SHIFT: u32 = 2;
+const CCMR_ICF_SHIFT: u32 = 4;
+const CCMR_CHANNEL_MASK: u32 = 0xff;
+
+// CCER fields of channel 1, the other channels are shifted by 4 each
+const CCER_CCE: u32 = 1 << 0;
+const CCER_CCP: u32 = 1 << 1;
+const CCER_CCNP: u32 = 1 << 3;
+
+fn ccmr_bits(config: &CaptureConfig) -> u32 {
+    let psc = match config.prescaler {
+        Prescaler::Div1 => 0b00,
+        Prescaler::Div2 => 0b01,
+        Prescaler::Div4 => 0b10,
+        Prescaler::Div8 => 0b11,
+    };
+    CCMR_CCS_INPUT | (psc << CCMR_ICPSC_SHIFT) | (u32::from(config.filter & 0xf) << CCMR_ICF_SHIFT)
+}
+
+fn ccer_polarity_bits(edge: Edge) -> u32 {
+    match edge {
+        Edge::Rising => 0,
+        Edge::Falling => CCER_CCP,
+        Edge::Both => CCER_CCP | CCER_CCNP,
+    }
+}
+
+macro_rules! capture {
+    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident, [
+        $($CX:ident: ($channelX:ident, $PINCX:ident, $ccmrX:ident, $ccrX:ident, $n:expr),)+
+    ]),)+) => {
+        $(
+            use crate::pac::$TIMX;
+
+            impl Capture<$TIMX> {
+                /// Configures the timer as free running counter for input capture
+                ///
+                /// The counter is incremented with `freq` and wraps around at 0xffff.
+                /// Frequencies above the timer clock are limited to the timer clock.
+                pub fn $timX<T>(tim: $TIMX, rcc: &mut Rcc, freq: T) -> Self
+                where
+                    T: Into<Hertz>,
+                {
+                    // enable and reset peripheral to a clean slate state
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIMX));
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+
+                    let psc = cast::u16((rcc.clocks.timclk().0 / freq.into().0).saturating_sub(1)).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| unsafe { w.bits(0xffff) });
+
+                    // Trigger update event to load the prescaler
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    Capture {
+                        clocks: rcc.clocks,
+                        tim,
+                    }
+                }
+
+                /// Returns the frequency the counter is incremented with
+                pub fn frequency(&self) -> Hertz {
+                    let psc = u32::from(self.tim.psc.read().psc().bits());
+                    Hertz(self.clocks.timclk().0 / (psc + 1))
+                }
+
+                /// Stops the timer and releases the TIM peripheral
+                pub fn release(self) -> $TIMX {
+                    let rcc = unsafe { &(*crate::pac::RCC::ptr()) };
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.ccer.reset();
+                    rcc.$apbenr.modify(|_, w| w.$timXen().clear_bit());
+                    clock_event!(disabled, stringify!($TIMX));
+                    self.tim
+                }
+
+                $(
+                    /// Configures a channel for input capture and enables it
+                    pub fn $channelX<PIN>(&mut self, _pin: PIN, config: CaptureConfig) -> CaptureChannel<$TIMX, $CX>
+                    where
+                        PIN: $PINCX<$TIMX>,
+                    {
+                        let mut channel: CaptureChannel<$TIMX, $CX> = CaptureChannel {
+                            _channel: PhantomData,
+                            _tim: PhantomData,
+                        };
+                        channel.configure(config);
+                        channel.enable();
+                        channel
+                    }
+                )+
+            }
+
+            $(
+                impl CaptureChannel<$TIMX, $CX> {
+                    const CCMR_SHIFT: u32 = ((($n) - 1) % 2) * 8;
+                    const CCER_SHIFT: u32 = (($n) - 1) * 4;
+                    const SR_CCIF: u32 = 1 << ($n);
+                    const SR_CCOF: u32 = 1 << (($n) + 8);
+
+                    /// Changes the channel configuration
+                    ///
+                    /// The channel is disabled while it is reconfigured.
+                    pub fn configure(&mut self, config: CaptureConfig) {
+                        //NOTE(unsafe) only this channel's fields are modified
+                        let tim = unsafe { &*$TIMX::ptr() };
+                        let enabled = self.is_enabled();
+                        // CCxS can only be written while the channel is disabled
+                        self.disable();
+                        tim.$ccmrX().modify(|r, w| unsafe {
+                            w.bits(
+                                (r.bits() & !(CCMR_CHANNEL_MASK << Self::CCMR_SHIFT))
+                                    | (ccmr_bits(&config) << Self::CCMR_SHIFT),
+                            )
+                        });
+                        self.set_edge(config.edge);
+                        if enabled {
+                            self.enable();
+                        }
+                    }
+
+                    /// Selects the edge triggering a capture
+                    pub fn set_edge(&mut self, edge: Edge) {
+                        //NOTE(unsafe) only this channel's fields are modified
+                        let tim = unsafe { &*$TIMX::ptr() };
+                        tim.ccer.modify(|r, w| unsafe {
+                            w.bits(
+                                (r.bits() & !((CCER_CCP | CCER_CCNP) << Self::CCER_SHIFT))
+                                    | (ccer_polarity_bits(edge) << Self::CCER_SHIFT),
+                            )
+                        });
+                    }
+
+                    /// Enables capturing
+                    pub fn enable(&mut self) {
+                        //NOTE(unsafe) atomic write with no side effects
+                        let tim = unsafe { &*$TIMX::ptr() };
+                        tim.ccer.modify(|r, w| unsafe { w.bits(r.bits() | (CCER_CCE << Self::CCER_SHIFT)) });
+                    }
+
+                    /// Disables capturing
+                    pub fn disable(&mut self) {
+                        //NOTE(unsafe) atomic write with no side effects
+                        let tim = unsafe { &*$TIMX::ptr() };
+                        tim.ccer.modify(|r, w| unsafe { w.bits(r.bits() & !(CCER_CCE << Self::CCER_SHIFT)) });
+                    }
+
+                    /// Returns true if capturing is enabled
+                    pub fn is_enabled(&self) -> bool {
+                        //NOTE(unsafe) atomic read with no side effects
+                        let tim = unsafe { &*$TIMX::ptr() };
+                        tim.ccer.read().bits() & (CCER_CCE << Self::CCER_SHIFT) != 0
+                    }
+
+                    /// Returns the latest captured counter value
+                    ///
+                    /// Returns [Error::Overcapture](crate::capture::Error::Overcapture)
+                    /// once if captures were lost since the last call, the next call
+                    /// returns the most recent capture.
+                    pub fn capture(&mut self) -> nb::Result<u16, Error> {
+                        //NOTE(unsafe) only this channel's flags are modified
+                        let tim = unsafe { &*$TIMX::ptr() };
+                        let sr = tim.sr.read().bits();
+                        if sr & Self::SR_CCOF != 0 {
+                            // The flags are cleared by writing 0, writing 1 has no effect
+                            tim.sr.write(|w| unsafe { w.bits(0xffff & !Self::SR_CCOF) });
+                            Err(nb::Error::Other(Error::Overcapture))
+                        } else if sr & Self::SR_CCIF != 0 {
+                            // Reading the capture register clears CCxIF
+                            Ok(tim.$ccrX().read().bits() as u16)
+                        } else {
+                            Err(nb::Error::WouldBlock)
+                        }
+                    }
+                }
+
+                impl CaptureInput for CaptureChannel<$TIMX, $CX> {
+                    fn capture(&mut self) -> nb::Result<u16, Error> {
+                        CaptureChannel::<$TIMX, $CX>::capture(self)
+                    }
+                }
+            )+
+        )+
+    };
+}
+
+capture! {
+    TIM1: (tim1, tim1en, tim1rst, apb2enr, apb2rstr, [
+        C1: (channel1, PinC1, ccmr1_input, ccr1, 1),
+        C2: (channel2, PinC2, ccmr1_input, ccr2, 2),
+        C3: (channel3, PinC3, ccmr2_input, ccr3, 3),
+        C4: (channel4, PinC4, ccmr2_input, ccr4, 4),
+    ]),
+    TIM3: (tim3, tim3en, tim3rst, apb1enr, apb1rstr, [
+        C1: (channel1, PinC1, ccmr1_input, ccr1, 1),
+        C2: (channel2, PinC2, ccmr1_input, ccr2, 2),
+        C3: (channel3, PinC3, ccmr2_input, ccr3, 3),
+        C4: (channel4, PinC4, ccmr2_input, ccr4, 4),
+    ]),
+    TIM14: (tim14, tim14en, tim14rst, apb1enr, apb1rstr, [
+        C1: (channel1, PinC1, ccmr1_input, ccr1, 1),
+    ]),
+    TIM16: (tim16, tim16en, tim16rst, apb2enr, apb2rstr, [
+        C1: (channel1, PinC1, ccmr1_input, ccr1, 1),
+    ]),
+    TIM17: (tim17, tim17en, tim17rst, apb2enr, apb2rstr, [
+        C1: (channel1, PinC1, ccmr1_input, ccr1, 1),
+    ]),
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+capture! {
+    TIM15: (tim15, tim15en, tim15rst, apb2enr, apb2rstr, [
+        C1: (channel1, PinC1, ccmr1_input, ccr1, 1),
+        C2: (channel2, PinC2, ccmr1_input, ccr2, 2),
+    ]),
+}
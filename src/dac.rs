@@ -1,7 +1,8 @@
 //! # API for the Digital to Analog converter
 //!
 //! Currently only supports writing to the DR of the DAC,
-//! just a basic one-shot conversion.
+//! just a basic one-shot conversion. [AudioSink] plays sample tables
+//! paced by TIM6.
 //!
 //! ## Example
 //! ``` no_run
@@ -70,10 +71,14 @@
 #![deny(unused_imports)]
 use core::mem;
 
+use embedded_hal::timer::CountDown;
+
 use crate::gpio::gpioa::{PA4, PA5};
 use crate::gpio::Analog;
-use crate::pac::DAC;
+use crate::pac::{DAC, TIM6};
 use crate::rcc::Rcc;
+use crate::time::Hertz;
+use crate::timers::{Event, Timer};
 
 pub struct C1;
 pub struct C2;
@@ -109,6 +114,7 @@ where
 {
     // Enable DAC clocks
     rcc.regs.apb1enr.modify(|_, w| w.dacen().set_bit());
+    clock_event!(enabled, "DAC");
 
     // Reset DAC
     rcc.regs.apb1rstr.modify(|_, w| w.dacrst().set_bit());
@@ -173,3 +179,102 @@ dac!(C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr);
     feature = "stm32f098",
 ))]
 dac!(C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr);
+
+/// Sample player writing PCM tables to a DAC channel
+///
+/// TIM6 paces the samples, [AudioSink::on_interrupt] has to be called from the
+/// TIM6 interrupt handler to output the next sample.
+pub struct AudioSink<CH> {
+    channel: CH,
+    timer: Timer<TIM6>,
+    samples: &'static [u16],
+    position: usize,
+    looping: bool,
+    playing: bool,
+    on_complete: Option<fn()>,
+}
+
+impl<CH> AudioSink<CH>
+where
+    CH: DacOut<u16> + DacPin,
+{
+    /// Creates a sink playing on `channel`, paced by `timer`
+    pub fn new(mut channel: CH, timer: Timer<TIM6>) -> Self {
+        channel.enable();
+        AudioSink {
+            channel,
+            timer,
+            samples: &[],
+            position: 0,
+            looping: false,
+            playing: false,
+            on_complete: None,
+        }
+    }
+
+    /// Starts playing `samples` at `sample_rate`
+    ///
+    /// Samples are right aligned 12 bit values. A running playback is replaced.
+    pub fn play<T>(&mut self, samples: &'static [u16], sample_rate: T)
+    where
+        T: Into<Hertz>,
+    {
+        self.samples = samples;
+        self.position = 0;
+        self.playing = !samples.is_empty();
+        if self.playing {
+            self.timer.start(sample_rate);
+            self.timer.clear_irq();
+            self.timer.listen(Event::TimeOut);
+        }
+    }
+
+    /// Restarts the samples from the beginning once the end is reached
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Sets a function called from the interrupt handler once playback ends
+    pub fn on_complete(&mut self, callback: Option<fn()>) {
+        self.on_complete = callback;
+    }
+
+    /// Stops playback, the DAC keeps its last value
+    pub fn stop(&mut self) {
+        self.timer.unlisten(Event::TimeOut);
+        self.playing = false;
+    }
+
+    /// Returns true while samples are played
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Outputs the next sample, call this from the TIM6 interrupt handler
+    pub fn on_interrupt(&mut self) {
+        self.timer.clear_irq();
+        if !self.playing {
+            return;
+        }
+
+        self.channel.set_value(self.samples[self.position]);
+        self.position += 1;
+
+        if self.position == self.samples.len() {
+            if self.looping {
+                self.position = 0;
+            } else {
+                self.stop();
+                if let Some(callback) = self.on_complete {
+                    callback();
+                }
+            }
+        }
+    }
+
+    /// Stops playback and releases the DAC channel and the timer
+    pub fn release(mut self) -> (CH, Timer<TIM6>) {
+        self.stop();
+        (self.channel, self.timer)
+    }
+}
@@ -0,0 +1,19 @@
+//! Helpers for the `debug_dump` methods of the peripherals
+
+use core::fmt::{Result, Write};
+
+/// Writes a register as raw value followed by the names of its set flag bits
+pub(crate) fn register<W: Write>(
+    w: &mut W,
+    name: &str,
+    value: u32,
+    flags: &[(u32, &str)],
+) -> Result {
+    write!(w, "{:<8} {:#010x}", name, value)?;
+    for &(bit, flag) in flags {
+        if value & (1 << bit) != 0 {
+            write!(w, " {}", flag)?;
+        }
+    }
+    writeln!(w)
+}
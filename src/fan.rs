@@ -0,0 +1,207 @@
+//! Closed loop control of a 4-wire PC fan
+//!
+//! [FanController] drives the PWM input of the fan with a PWM channel and
+//! measures the speed from the tachometer output with an input capture
+//! channel. A PI loop adjusts the duty cycle towards the target speed.
+//!
+//! # Example
+//! ``` no_run
+//! use stm32f0xx_hal as hal;
+//!
+//! use crate::hal::pac;
+//! use crate::hal::prelude::*;
+//! use crate::hal::capture::{Capture, CaptureConfig};
+//! use crate::hal::fan::{FanController, PiGains};
+//! use crate::hal::pwm;
+//!
+//! cortex_m::interrupt::free(|cs| {
+//!     let mut p = pac::Peripherals::take().unwrap();
+//!     let mut rcc = p.RCC.configure().sysclk(48.mhz()).freeze(&mut p.FLASH);
+//!
+//!     let gpioa = p.GPIOA.split(&mut rcc);
+//!     let pwm_pin = gpioa.pa8.into_alternate_af2(cs);
+//!     let tach_pin = gpioa.pa6.into_alternate_af1(cs);
+//!
+//!     // Fans expect a 25kHz PWM signal
+//!     let pwm = pwm::tim1(p.TIM1, pwm_pin, &mut rcc, 25.khz());
+//!
+//!     // Count with 100kHz, periods up to 650ms can be measured
+//!     let mut capture = Capture::tim3(p.TIM3, &mut rcc, 100.khz());
+//!     let tach = capture.channel1(tach_pin, CaptureConfig::default());
+//!
+//!     let mut fan = FanController::new(pwm, tach, 100.khz(), 2, PiGains::default());
+//!     fan.set_target_rpm(1200);
+//!     loop {
+//!         // Call regularly, e.g. every 100ms
+//!         fan.update();
+//!     }
+//! });
+//! ```
+
+use embedded_hal::PwmPin;
+
+use crate::capture::CaptureInput;
+use crate::time::Hertz;
+
+/// Number of updates without tachometer pulse after which the fan is stalled
+const STALL_UPDATES: u8 = 4;
+
+/// Gains of the PI loop
+///
+/// The gains are fixed point numbers with 8 fractional bits, i.e. a gain of
+/// 256 changes the duty cycle by one count per rpm of speed error.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PiGains {
+    /// Proportional gain
+    pub kp: i32,
+    /// Integral gain, applied once per update
+    pub ki: i32,
+}
+
+impl Default for PiGains {
+    fn default() -> Self {
+        PiGains { kp: 64, ki: 16 }
+    }
+}
+
+/// PWM output and tachometer input of a fan with a PI speed loop
+pub struct FanController<PWM, TACH> {
+    pwm: PWM,
+    tach: TACH,
+    tach_freq: u32,
+    pulses_per_rev: u32,
+    gains: PiGains,
+    target_rpm: u32,
+    rpm: u32,
+    last_capture: Option<u16>,
+    period: Option<u32>,
+    missed: u8,
+    integral: i32,
+}
+
+impl<PWM, TACH> FanController<PWM, TACH>
+where
+    PWM: PwmPin<Duty = u16>,
+    TACH: CaptureInput,
+{
+    /// Creates the controller with the fan stopped
+    ///
+    /// `tach_freq` is the tick rate of the capture timer and `pulses_per_rev`
+    /// the number of tachometer pulses per revolution, usually 2. The
+    /// slowest tachometer period has to fit into 16 bit ticks.
+    pub fn new<T>(
+        mut pwm: PWM,
+        tach: TACH,
+        tach_freq: T,
+        pulses_per_rev: u8,
+        gains: PiGains,
+    ) -> Self
+    where
+        T: Into<Hertz>,
+    {
+        assert!(pulses_per_rev > 0);
+        pwm.set_duty(0);
+        pwm.enable();
+        FanController {
+            pwm,
+            tach,
+            tach_freq: tach_freq.into().0,
+            pulses_per_rev: u32::from(pulses_per_rev),
+            gains,
+            target_rpm: 0,
+            rpm: 0,
+            last_capture: None,
+            period: None,
+            missed: 0,
+            integral: 0,
+        }
+    }
+
+    /// Sets the speed the loop regulates to, 0 stops the fan
+    pub fn set_target_rpm(&mut self, rpm: u32) {
+        self.target_rpm = rpm;
+    }
+
+    /// Returns the target speed
+    pub fn target_rpm(&self) -> u32 {
+        self.target_rpm
+    }
+
+    /// Returns the speed measured by the last update
+    pub fn rpm(&self) -> u32 {
+        self.rpm
+    }
+
+    /// Returns true if no tachometer pulses were seen for several updates
+    /// while the fan is driven
+    pub fn is_stalled(&self) -> bool {
+        self.missed >= STALL_UPDATES && self.pwm.get_duty() > 0
+    }
+
+    /// Changes the gains of the PI loop
+    pub fn set_gains(&mut self, gains: PiGains) {
+        self.gains = gains;
+    }
+
+    /// Reads pending tachometer captures
+    ///
+    /// Called by [update](Self::update), call it more often if the capture
+    /// interval is shorter than the update interval.
+    pub fn poll_tach(&mut self) {
+        loop {
+            match self.tach.capture() {
+                Ok(now) => {
+                    if let Some(last) = self.last_capture {
+                        self.period = Some(u32::from(now.wrapping_sub(last)));
+                    }
+                    self.last_capture = Some(now);
+                }
+                // Lost captures corrupt the next period, start over
+                Err(nb::Error::Other(_)) => self.last_capture = None,
+                Err(nb::Error::WouldBlock) => break,
+            }
+        }
+    }
+
+    /// Measures the speed and adjusts the duty cycle
+    ///
+    /// Has to be called at a fixed interval, the integral gain is applied once
+    /// per call.
+    pub fn update(&mut self) {
+        self.poll_tach();
+
+        match self.period.take() {
+            Some(period) if period > 0 => {
+                self.rpm = 60 * self.tach_freq / (period * self.pulses_per_rev);
+                self.missed = 0;
+            }
+            _ => {
+                self.missed = self.missed.saturating_add(1);
+                if self.missed >= STALL_UPDATES {
+                    self.rpm = 0;
+                    self.last_capture = None;
+                }
+            }
+        }
+
+        let max_duty = i32::from(self.pwm.get_max_duty());
+        if self.target_rpm == 0 {
+            self.integral = 0;
+            self.pwm.set_duty(0);
+            return;
+        }
+
+        let error = self.target_rpm as i32 - self.rpm as i32;
+        // Clamp the integral to avoid windup while the output saturates
+        self.integral = (self.integral + error * self.gains.ki).clamp(0, max_duty << 8);
+        let duty = (error * self.gains.kp + self.integral) >> 8;
+        self.pwm.set_duty(duty.clamp(0, max_duty) as u16);
+    }
+
+    /// Stops the fan and releases the PWM and capture channels
+    pub fn release(mut self) -> (PWM, TACH) {
+        self.pwm.set_duty(0);
+        self.pwm.disable();
+        (self.pwm, self.tach)
+    }
+}
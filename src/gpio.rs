@@ -1,8 +1,11 @@
 //! General Purpose Input / Output
 
 use core::convert::Infallible;
+use core::fmt;
 use core::marker::PhantomData;
 
+use cortex_m::interrupt::CriticalSection;
+
 use crate::rcc::Rcc;
 
 /// Extension trait to split a GPIO peripheral in independent pins and registers
@@ -38,6 +41,63 @@ pub struct AF6;
 /// Alternate function 7
 pub struct AF7;
 
+/// Alternate function selectable by its number
+pub trait AlternateFunction {
+    /// Value of the function in the AFRx registers
+    const NUMBER: u32;
+}
+
+macro_rules! alternate_function {
+    ($($AF:ident: $n:expr,)+) => {
+        $(
+            impl AlternateFunction for $AF {
+                const NUMBER: u32 = $n;
+            }
+        )+
+    };
+}
+
+alternate_function! {
+    AF0: 0,
+    AF1: 1,
+    AF2: 2,
+    AF3: 3,
+    AF4: 4,
+    AF5: 5,
+    AF6: 6,
+    AF7: 7,
+}
+
+/// Pin that can be connected to `SIGNAL` of the peripheral `PERIPH`
+///
+/// Implemented for the pins in any mode, so peripheral constructors accepting
+/// a `ValidPin` select the correct alternate function themselves.
+pub trait ValidPin<PERIPH, SIGNAL> {
+    /// The pin configured for the signal
+    type Configured;
+
+    /// Switches the pin to the alternate function of the signal
+    fn setup(self, cs: &CriticalSection) -> Self::Configured;
+}
+
+/// Peripheral signals used with [ValidPin]
+pub mod signal {
+    /// USART transmit
+    pub struct Tx;
+    /// USART receive
+    pub struct Rx;
+    /// SPI clock
+    pub struct Sck;
+    /// SPI master in, slave out
+    pub struct Miso;
+    /// SPI master out, slave in
+    pub struct Mosi;
+    /// I2C clock
+    pub struct Scl;
+    /// I2C data
+    pub struct Sda;
+}
+
 /// Alternate function mode (type state)
 pub struct Alternate<AF> {
     _mode: PhantomData<AF>,
@@ -144,6 +204,564 @@ impl<MODE> InputPin for Pin<Input<MODE>> {
     }
 }
 
+/// Erased alternate function pin accepted by the peripheral constructors
+///
+/// The type of an erased pin no longer tells which peripheral signals it can
+/// carry, so this wrapper vouches for it instead. This lets board support
+/// crates keep their pins in arrays or structs of a single type.
+pub struct Unchecked<PIN>(PIN);
+
+impl<PIN> Unchecked<PIN> {
+    /// Wraps an erased pin for a peripheral constructor
+    ///
+    /// # Safety
+    ///
+    /// The pin must be routed to the signal of the peripheral it is passed to
+    /// and be set to the matching alternate function. Otherwise the peripheral
+    /// drives or samples the wrong pin.
+    pub unsafe fn new(pin: PIN) -> Self {
+        Unchecked(pin)
+    }
+
+    /// Returns the wrapped pin, e.g. after releasing the peripheral
+    pub fn into_inner(self) -> PIN {
+        self.0
+    }
+}
+
+/// Fully erased pin without dynamic dispatch
+///
+/// Same as [Pin], but the port is stored as an index instead of a trait
+/// object. All GPIO ports share the register layout of GPIOA, so the accesses
+/// compile to a direct register access without a vtable.
+pub struct ErasedPin<MODE> {
+    // port index in the upper, pin number in the lower four bits
+    pin_port: u8,
+    _mode: PhantomData<MODE>,
+}
+
+// Distance between the register blocks of two consecutive GPIO ports
+const GPIO_PORT_STRIDE: usize = 0x400;
+
+impl<MODE> ErasedPin<MODE> {
+    fn new(port: *const crate::pac::gpioa::RegisterBlock, i: u8) -> Self {
+        let index = (port as usize - crate::pac::GPIOA::ptr() as usize) / GPIO_PORT_STRIDE;
+        ErasedPin {
+            pin_port: ((index as u8) << 4) | i,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Returns the pin number within its port
+    #[inline(always)]
+    pub fn pin_id(&self) -> u8 {
+        self.pin_port & 0x0f
+    }
+
+    /// Returns the index of the port, 0 for GPIOA
+    #[inline(always)]
+    pub fn port_id(&self) -> u8 {
+        self.pin_port >> 4
+    }
+
+    #[inline(always)]
+    fn block(&self) -> &crate::pac::gpioa::RegisterBlock {
+        let base = crate::pac::GPIOA::ptr() as usize;
+        // NOTE(unsafe) the port index was taken from an existing GPIO port
+        unsafe {
+            &*((base + usize::from(self.port_id()) * GPIO_PORT_STRIDE)
+                as *const crate::pac::gpioa::RegisterBlock)
+        }
+    }
+}
+
+impl<MODE> StatefulOutputPin for ErasedPin<Output<MODE>> {
+    #[inline(always)]
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        self.is_set_low().map(|v| !v)
+    }
+
+    #[inline(always)]
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.block().is_set_low(self.pin_id()))
+    }
+}
+
+impl<MODE> OutputPin for ErasedPin<Output<MODE>> {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.block().set_high(self.pin_id());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.block().set_low(self.pin_id());
+        Ok(())
+    }
+}
+
+impl<MODE> toggleable::Default for ErasedPin<Output<MODE>> {}
+
+impl InputPin for ErasedPin<Output<OpenDrain>> {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.is_low().map(|v| !v)
+    }
+
+    #[inline(always)]
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.block().is_low(self.pin_id()))
+    }
+}
+
+impl<MODE> InputPin for ErasedPin<Input<MODE>> {
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.is_low().map(|v| !v)
+    }
+
+    #[inline(always)]
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.block().is_low(self.pin_id()))
+    }
+}
+
+/// Levels of all pins of a GPIO port, bit `n` is pin `n`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PortSnapshot(pub u16);
+
+impl PortSnapshot {
+    /// Returns the pins which changed from high to low since `previous`
+    ///
+    /// With pull-ups on the inputs these are the newly pressed keys.
+    #[inline(always)]
+    pub fn falling_since(self, previous: PortSnapshot) -> u16 {
+        previous.0 & !self.0
+    }
+
+    /// Returns the pins which changed from low to high since `previous`
+    ///
+    /// With pull-ups on the inputs these are the released keys.
+    #[inline(always)]
+    pub fn rising_since(self, previous: PortSnapshot) -> u16 {
+        !previous.0 & self.0
+    }
+
+    /// Returns the pins that changed in either direction since `previous`
+    #[inline(always)]
+    pub fn changed_since(self, previous: PortSnapshot) -> u16 {
+        previous.0 ^ self.0
+    }
+}
+
+/// Read access to the input register of a whole GPIO port
+///
+/// Reading the input register has no side effects, so the reader can be
+/// copied freely, e.g. into a timer interrupt scanning a keypad.
+#[derive(Clone, Copy)]
+pub struct PortReader {
+    port_id: u8,
+}
+
+impl PortReader {
+    fn new(port: *const crate::pac::gpioa::RegisterBlock) -> Self {
+        let index = (port as usize - crate::pac::GPIOA::ptr() as usize) / GPIO_PORT_STRIDE;
+        PortReader {
+            port_id: index as u8,
+        }
+    }
+
+    /// Returns the levels of all pins of the port
+    #[inline(always)]
+    pub fn snapshot(&self) -> PortSnapshot {
+        let base = crate::pac::GPIOA::ptr() as usize;
+        // NOTE(unsafe) atomic read with no side effects
+        let block = unsafe {
+            &*((base + usize::from(self.port_id) * GPIO_PORT_STRIDE)
+                as *const crate::pac::gpioa::RegisterBlock)
+        };
+        PortSnapshot(block.idr.read().bits() as u16)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Pin which can be reconfigured in a batch with [configure]
+///
+/// Sealed, it is implemented for the GPIO pins of this crate only.
+pub trait ConfigurablePin: sealed::Sealed {
+    /// The same pin in mode `MODE`
+    type Mode<MODE>;
+
+    #[doc(hidden)]
+    fn port_pin(&self) -> (usize, u8);
+
+    #[doc(hidden)]
+    unsafe fn into_mode<MODE>(self) -> Self::Mode<MODE>;
+}
+
+// Number of GPIO ports from GPIOA to GPIOF
+const GPIO_PORTS: usize = 6;
+
+// Bits to replace in a register
+#[derive(Clone, Copy, Default)]
+struct Field {
+    mask: u32,
+    value: u32,
+}
+
+impl Field {
+    fn set(&mut self, mask: u32, value: u32, shift: u32) {
+        self.mask |= mask << shift;
+        self.value = (self.value & !(mask << shift)) | (value << shift);
+    }
+
+    fn apply(self, bits: u32) -> u32 {
+        (bits & !self.mask) | self.value
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct PortConfig {
+    moder: Field,
+    otyper: Field,
+    pupdr: Field,
+    afrl: Field,
+    afrh: Field,
+}
+
+/// Pin configurations collected by [configure]
+pub struct Config {
+    ports: [PortConfig; GPIO_PORTS],
+}
+
+macro_rules! config_alternate {
+    ($($name:ident: $AF:ident,)+) => {
+        $(
+            /// Configures the pin in the alternate function mode
+            pub fn $name<P: ConfigurablePin>(&mut self, pin: P) -> P::Mode<Alternate<$AF>> {
+                self.alternate(pin)
+            }
+        )+
+    };
+}
+
+impl Config {
+    fn port(&mut self, port: usize) -> &mut PortConfig {
+        &mut self.ports[port]
+    }
+
+    fn mode<P: ConfigurablePin, MODE>(&mut self, pin: P, moder: u32, pupdr: u32) -> P::Mode<MODE> {
+        let (port, i) = pin.port_pin();
+        let port = self.port(port);
+        port.moder.set(0b11, moder, 2 * u32::from(i));
+        port.pupdr.set(0b11, pupdr, 2 * u32::from(i));
+        // NOTE(unsafe) the registers are written before `configure` returns
+        unsafe { pin.into_mode() }
+    }
+
+    fn output<P: ConfigurablePin, MODE>(&mut self, pin: P, open_drain: bool) -> P::Mode<MODE> {
+        let (port, i) = pin.port_pin();
+        self.port(port)
+            .otyper
+            .set(0b1, open_drain as u32, u32::from(i));
+        self.mode(pin, 0b01, 0b00)
+    }
+
+    /// Configures the pin in the alternate function mode `AF`
+    pub fn alternate<AF: AlternateFunction, P: ConfigurablePin>(
+        &mut self,
+        pin: P,
+    ) -> P::Mode<Alternate<AF>> {
+        let (port, i) = pin.port_pin();
+        let port = self.port(port);
+        if i < 8 {
+            port.afrl.set(0b1111, AF::NUMBER, 4 * u32::from(i));
+        } else {
+            port.afrh.set(0b1111, AF::NUMBER, 4 * u32::from(i - 8));
+        }
+        port.moder.set(0b11, 0b10, 2 * u32::from(i));
+        // NOTE(unsafe) the registers are written before `configure` returns
+        unsafe { pin.into_mode() }
+    }
+
+    config_alternate! {
+        alternate_af0: AF0,
+        alternate_af1: AF1,
+        alternate_af2: AF2,
+        alternate_af3: AF3,
+        alternate_af4: AF4,
+        alternate_af5: AF5,
+        alternate_af6: AF6,
+        alternate_af7: AF7,
+    }
+
+    /// Configures the pin as push pull output
+    pub fn push_pull<P: ConfigurablePin>(&mut self, pin: P) -> P::Mode<Output<PushPull>> {
+        self.output(pin, false)
+    }
+
+    /// Configures the pin as open drain output
+    pub fn open_drain<P: ConfigurablePin>(&mut self, pin: P) -> P::Mode<Output<OpenDrain>> {
+        self.output(pin, true)
+    }
+
+    /// Configures the pin as floating input
+    pub fn floating_input<P: ConfigurablePin>(&mut self, pin: P) -> P::Mode<Input<Floating>> {
+        self.mode(pin, 0b00, 0b00)
+    }
+
+    /// Configures the pin as pulled up input
+    pub fn pull_up_input<P: ConfigurablePin>(&mut self, pin: P) -> P::Mode<Input<PullUp>> {
+        self.mode(pin, 0b00, 0b01)
+    }
+
+    /// Configures the pin as pulled down input
+    pub fn pull_down_input<P: ConfigurablePin>(&mut self, pin: P) -> P::Mode<Input<PullDown>> {
+        self.mode(pin, 0b00, 0b10)
+    }
+
+    /// Configures the pin as analog pin
+    pub fn analog<P: ConfigurablePin>(&mut self, pin: P) -> P::Mode<Analog> {
+        self.mode(pin, 0b11, 0b00)
+    }
+}
+
+/// Configures several pins with one register write per port and register
+///
+/// The closure converts the pins and returns them, the registers are written
+/// when it returns. This shortens the initialization of many pins compared to
+/// the `into_*` methods, which modify the registers for every pin.
+///
+/// ``` no_run
+/// # use stm32f0xx_hal::{gpio, pac, prelude::*};
+/// # let mut p = pac::Peripherals::take().unwrap();
+/// # let mut rcc = p.RCC.configure().freeze(&mut p.FLASH);
+/// let gpioa = p.GPIOA.split(&mut rcc);
+/// let (led, tx, rx) = cortex_m::interrupt::free(|cs| {
+///     gpio::configure(cs, |cfg| {
+///         (
+///             cfg.push_pull(gpioa.pa1),
+///             cfg.alternate_af1(gpioa.pa2),
+///             cfg.alternate_af1(gpioa.pa3),
+///         )
+///     })
+/// });
+/// ```
+pub fn configure<F, R>(_cs: &CriticalSection, f: F) -> R
+where
+    F: FnOnce(&mut Config) -> R,
+{
+    let mut config = Config {
+        ports: [PortConfig::default(); GPIO_PORTS],
+    };
+    let pins = f(&mut config);
+
+    let base = crate::pac::GPIOA::ptr() as usize;
+    for (index, port) in config.ports.iter().enumerate() {
+        if port.moder.mask == 0 {
+            continue;
+        }
+        // NOTE(unsafe) only ports with pins handed to the closure are modified,
+        // under the critical section
+        unsafe {
+            let reg =
+                &*((base + index * GPIO_PORT_STRIDE) as *const crate::pac::gpioa::RegisterBlock);
+            if port.pupdr.mask != 0 {
+                reg.pupdr.modify(|r, w| w.bits(port.pupdr.apply(r.bits())));
+            }
+            if port.otyper.mask != 0 {
+                reg.otyper
+                    .modify(|r, w| w.bits(port.otyper.apply(r.bits())));
+            }
+            if port.afrl.mask != 0 {
+                reg.afrl.modify(|r, w| w.bits(port.afrl.apply(r.bits())));
+            }
+            if port.afrh.mask != 0 {
+                reg.afrh.modify(|r, w| w.bits(port.afrh.apply(r.bits())));
+            }
+            reg.moder.modify(|r, w| w.bits(port.moder.apply(r.bits())));
+        }
+    }
+
+    pins
+}
+
+/// Signal names of some common alternate functions
+#[cfg(feature = "gpio-af-names")]
+const KNOWN_FUNCTIONS: &[(char, u8, u8, &str)] = &[
+    // Devices without USART2 route USART1 to PA2/PA3
+    #[cfg(any(
+        feature = "stm32f030x4",
+        feature = "stm32f030x6",
+        feature = "stm32f031",
+        feature = "stm32f038",
+    ))]
+    ('A', 2, 1, "USART1_TX"),
+    #[cfg(any(
+        feature = "stm32f030x4",
+        feature = "stm32f030x6",
+        feature = "stm32f031",
+        feature = "stm32f038",
+    ))]
+    ('A', 3, 1, "USART1_RX"),
+    #[cfg(not(any(
+        feature = "stm32f030x4",
+        feature = "stm32f030x6",
+        feature = "stm32f031",
+        feature = "stm32f038",
+    )))]
+    ('A', 2, 1, "USART2_TX"),
+    #[cfg(not(any(
+        feature = "stm32f030x4",
+        feature = "stm32f030x6",
+        feature = "stm32f031",
+        feature = "stm32f038",
+    )))]
+    ('A', 3, 1, "USART2_RX"),
+    ('A', 5, 0, "SPI1_SCK"),
+    ('A', 6, 0, "SPI1_MISO"),
+    ('A', 7, 0, "SPI1_MOSI"),
+    ('A', 8, 2, "TIM1_CH1"),
+    ('A', 9, 1, "USART1_TX"),
+    ('A', 10, 1, "USART1_RX"),
+    ('A', 13, 0, "SWDIO"),
+    ('A', 14, 0, "SWCLK"),
+    ('B', 3, 0, "SPI1_SCK"),
+    ('B', 4, 0, "SPI1_MISO"),
+    ('B', 5, 0, "SPI1_MOSI"),
+    ('B', 6, 1, "I2C1_SCL"),
+    ('B', 7, 1, "I2C1_SDA"),
+    ('B', 8, 1, "I2C1_SCL"),
+    ('B', 9, 1, "I2C1_SDA"),
+];
+
+/// Writes the configuration of every pin of the ports with enabled clock
+///
+/// Lists mode, alternate function number, output type and pull of each pin,
+/// e.g. to check the init code against the schematic when a peripheral stays
+/// silent. With the `gpio-af-names` feature the signal names of some common
+/// alternate functions are listed too, others have to be looked up in the
+/// datasheet.
+pub fn debug_dump<W: fmt::Write>(rcc: &Rcc, w: &mut W) -> fmt::Result {
+    let ahbenr = rcc.regs.ahbenr.read().bits();
+    let base = crate::pac::GPIOA::ptr() as usize;
+    for index in 0..GPIO_PORTS {
+        // Ports without clock read as zero, unavailable ports are never enabled
+        if ahbenr & (1 << (17 + index)) == 0 {
+            continue;
+        }
+        let port = (b'A' + index as u8) as char;
+        // NOTE(unsafe) atomic reads with no side effects
+        let reg = unsafe {
+            &*((base + index * GPIO_PORT_STRIDE) as *const crate::pac::gpioa::RegisterBlock)
+        };
+        let moder = reg.moder.read().bits();
+        let otyper = reg.otyper.read().bits();
+        let pupdr = reg.pupdr.read().bits();
+        let afr = u64::from(reg.afrl.read().bits()) | (u64::from(reg.afrh.read().bits()) << 32);
+
+        for pin in 0..16u8 {
+            write!(w, "P{}{:<3}", port, pin)?;
+            let mode = (moder >> (2 * pin)) & 0b11;
+            match mode {
+                0b00 => write!(w, "input    ")?,
+                0b01 => write!(w, "output   ")?,
+                0b10 => {
+                    let af = ((afr >> (4 * pin)) & 0xf) as u8;
+                    write!(w, "AF{:<7}", af)?;
+                    #[cfg(feature = "gpio-af-names")]
+                    {
+                        let known = KNOWN_FUNCTIONS
+                            .iter()
+                            .find(|&&(p, i, f, _)| p == port && i == pin && f == af);
+                        if let Some(&(_, _, _, name)) = known {
+                            write!(w, "{:<10}", name)?;
+                        }
+                    }
+                }
+                _ => write!(w, "analog   ")?,
+            }
+            if mode == 0b01 || mode == 0b10 {
+                if otyper & (1 << pin) != 0 {
+                    write!(w, " open-drain")?;
+                } else {
+                    write!(w, " push-pull")?;
+                }
+            }
+            match (pupdr >> (2 * pin)) & 0b11 {
+                0b01 => writeln!(w, " pull-up")?,
+                0b10 => writeln!(w, " pull-down")?,
+                _ => writeln!(w)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Open drain pin on a line shared with other devices
+///
+/// On a wired-AND line, e.g. a shared interrupt or an I2C ALERT line, the pin
+/// only ever pulls the line low or releases it, the line level is set by the
+/// pull-up and the other devices. The methods name these states explicitly
+/// instead of the `set_high`/`is_high` of the output pin, which refer to the
+/// output register and the line level respectively.
+pub struct OpenDrainBus<PIN> {
+    pin: PIN,
+}
+
+impl<PIN> OpenDrainBus<PIN>
+where
+    PIN: OutputPin<Error = Infallible> + StatefulOutputPin + InputPin<Error = Infallible>,
+{
+    /// Wraps an open drain output pin and releases the line
+    pub fn new(mut pin: PIN) -> Self {
+        pin.set_high().unwrap();
+        OpenDrainBus { pin }
+    }
+
+    /// Stops driving the line, it is pulled high unless another device drives it
+    pub fn release_line(&mut self) {
+        self.pin.set_high().unwrap();
+    }
+
+    /// Pulls the line low
+    pub fn drive_low(&mut self) {
+        self.pin.set_low().unwrap();
+    }
+
+    /// Returns true if this pin pulls the line low
+    pub fn is_driving_low(&self) -> bool {
+        self.pin.is_set_low().unwrap()
+    }
+
+    /// Returns the level of the line as seen on the pin
+    pub fn line_is_high(&self) -> bool {
+        self.pin.is_high().unwrap()
+    }
+
+    /// Returns true if the line is low although this pin released it, i.e.
+    /// another device drives the line
+    pub fn is_held_by_other(&self) -> bool {
+        !self.is_driving_low() && !self.line_is_high()
+    }
+
+    /// Releases the line and returns the pin
+    pub fn free(mut self) -> PIN {
+        self.release_line();
+        self.pin
+    }
+}
+
 macro_rules! gpio_trait {
     ($gpiox:ident) => {
         impl GpioRegExt for crate::pac::$gpiox::RegisterBlock {
@@ -195,7 +813,8 @@ macro_rules! gpio {
                 use super::{
                     Alternate, Analog, Floating, GpioExt, Input, OpenDrain, Output,
                     PullDown, PullUp, PushPull, AF0, AF1, AF2, AF3, AF4, AF5, AF6, AF7,
-                    Pin, GpioRegExt,
+                    AlternateFunction, Pin, ErasedPin, GpioRegExt, PortReader, PortSnapshot,
+                    ConfigurablePin, GPIO_PORT_STRIDE,
                 };
 
                 /// GPIO parts
@@ -206,11 +825,26 @@ macro_rules! gpio {
                     )+
                 }
 
+                impl Parts {
+                    /// Returns a reader for the input register of the port
+                    ///
+                    /// Take the reader before moving the pins out of the parts.
+                    pub fn reader(&self) -> PortReader {
+                        PortReader::new($GPIOX::ptr() as *const _)
+                    }
+
+                    /// Returns the levels of all pins of the port
+                    pub fn snapshot(&self) -> PortSnapshot {
+                        self.reader().snapshot()
+                    }
+                }
+
                 impl GpioExt for $GPIOX {
                     type Parts = Parts;
 
                     fn split(self, rcc: &mut Rcc) -> Parts {
                         rcc.regs.ahbenr.modify(|_, w| w.$iopxenr().set_bit());
+                        clock_event!(enabled, stringify!($GPIOX));
 
                         Parts {
                             $(
@@ -248,7 +882,40 @@ macro_rules! gpio {
                         _mode: PhantomData<MODE>,
                     }
 
+                    impl<MODE> super::sealed::Sealed for $PXi<MODE> {}
+
+                    impl<MODE> ConfigurablePin for $PXi<MODE> {
+                        type Mode<M> = $PXi<M>;
+
+                        fn port_pin(&self) -> (usize, u8) {
+                            let base = crate::pac::GPIOA::ptr() as usize;
+                            ((($GPIOX::ptr() as usize) - base) / GPIO_PORT_STRIDE, $i)
+                        }
+
+                        unsafe fn into_mode<M>(self) -> $PXi<M> {
+                            $PXi { _mode: PhantomData }
+                        }
+                    }
+
                     impl<MODE> $PXi<MODE> {
+                        /// Creates the pin without splitting the port, e.g. for `static` items
+                        ///
+                        /// # Safety
+                        ///
+                        /// The pin must already be configured as `MODE` and must not be in use
+                        /// anywhere else.
+                        pub const unsafe fn new_unchecked() -> Self {
+                            $PXi { _mode: PhantomData }
+                        }
+
+                        /// Configures the pin to operate in the alternate function mode `AF`
+                        pub fn into_alternate<AF: AlternateFunction>(
+                            self, _cs: &CriticalSection
+                        ) -> $PXi<Alternate<AF>> {
+                            _set_alternate_mode($i, AF::NUMBER);
+                            $PXi { _mode: PhantomData }
+                        }
+
                         /// Configures the pin to operate in AF0 mode
                         pub fn into_alternate_af0(
                             self, _cs: &CriticalSection
@@ -475,6 +1142,27 @@ macro_rules! gpio {
                         }
                     }
 
+                    impl<AF> $PXi<Alternate<AF>> {
+                        /// Erases the pin number from the type
+                        ///
+                        /// Peripheral constructors only accept the erased pin wrapped in
+                        /// [Unchecked](super::Unchecked).
+                        pub fn downgrade(self) -> Pin<Alternate<AF>> {
+                            Pin {
+                                i: $i,
+                                port: $GPIOX::ptr() as *const dyn GpioRegExt,
+                                _mode: self._mode,
+                            }
+                        }
+
+                        /// Erases the port and pin number from the type
+                        ///
+                        /// Like `downgrade`, but without dynamic dispatch on every access
+                        pub fn erase(self) -> ErasedPin<Alternate<AF>> {
+                            ErasedPin::new($GPIOX::ptr() as *const _, $i)
+                        }
+                    }
+
                     impl<AF> $PXi<Alternate<AF>> {
                         /// Turns pin alternate configuration pin into open drain
                         pub fn set_open_drain(self, _cs: &CriticalSection) -> Self {
@@ -501,6 +1189,13 @@ macro_rules! gpio {
                                 _mode: self._mode,
                             }
                         }
+
+                        /// Erases the port and pin number from the type
+                        ///
+                        /// Like `downgrade`, but without dynamic dispatch on every access
+                        pub fn erase(self) -> ErasedPin<Output<MODE>> {
+                            ErasedPin::new($GPIOX::ptr() as *const _, $i)
+                        }
                     }
 
                     impl<MODE> StatefulOutputPin for $PXi<Output<MODE>> {
@@ -551,6 +1246,13 @@ macro_rules! gpio {
                                 _mode: self._mode,
                             }
                         }
+
+                        /// Erases the port and pin number from the type
+                        ///
+                        /// Like `downgrade`, but without dynamic dispatch on every access
+                        pub fn erase(self) -> ErasedPin<Input<MODE>> {
+                            ErasedPin::new($GPIOX::ptr() as *const _, $i)
+                        }
                     }
 
                     impl<MODE> InputPin for $PXi<Input<MODE>> {
@@ -0,0 +1,121 @@
+//! Moving peripherals from the main thread into an interrupt handler
+//!
+//! A [Channel] is a `static` one-shot cell: the main thread sends a value
+//! exactly once, the interrupt handler takes it exactly once and owns it from
+//! then on. This replaces the `Mutex<RefCell<Option<T>>>` pattern together
+//! with the critical section on every interrupt.
+//!
+//! Like the peripherals of the PAC, the [Sender] of a channel can be taken
+//! only once. Sending consumes it, so a second send does not compile.
+//!
+//! # Example
+//! ``` no_run
+//! use stm32f0xx_hal as hal;
+//!
+//! use crate::hal::handoff::Channel;
+//! use crate::hal::pac::{self, interrupt, TIM7};
+//! use crate::hal::prelude::*;
+//! use crate::hal::timers::{Event, Timer};
+//!
+//! static TIMER: Channel<Timer<TIM7>> = Channel::new();
+//!
+//! let mut p = pac::Peripherals::take().unwrap();
+//! let mut rcc = p.RCC.configure().freeze(&mut p.FLASH);
+//!
+//! let mut timer = Timer::tim7(p.TIM7, 1.hz(), &mut rcc);
+//! timer.listen(Event::TimeOut);
+//! TIMER.sender().unwrap().send(timer);
+//!
+//! #[interrupt]
+//! fn TIM7() {
+//!     static mut LOCAL: Option<Timer<TIM7>> = None;
+//!
+//!     if let Some(timer) = TIMER.receive(LOCAL) {
+//!         timer.wait().ok();
+//!     }
+//! }
+//! ```
+
+use core::cell::{Cell, RefCell};
+
+use cortex_m::interrupt::{self, Mutex};
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Empty,
+    SenderTaken,
+    Sent,
+}
+
+/// One-shot cell handing a value over to an interrupt handler
+pub struct Channel<T> {
+    value: Mutex<RefCell<Option<T>>>,
+    state: Mutex<Cell<State>>,
+}
+
+/// The sending half of a [Channel], consumed by sending
+pub struct Sender<'a, T> {
+    channel: &'a Channel<T>,
+}
+
+impl<T> Channel<T> {
+    /// Creates an empty channel
+    pub const fn new() -> Self {
+        Channel {
+            value: Mutex::new(RefCell::new(None)),
+            state: Mutex::new(Cell::new(State::Empty)),
+        }
+    }
+
+    /// Returns the sender on the first call, `None` afterwards
+    pub fn sender(&self) -> Option<Sender<'_, T>> {
+        interrupt::free(|cs| {
+            let state = self.state.borrow(cs);
+            if state.get() == State::Empty {
+                state.set(State::SenderTaken);
+                Some(Sender { channel: self })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Takes the value out of the channel
+    ///
+    /// Returns `None` before the value was sent and after it was taken.
+    pub fn take(&self) -> Option<T> {
+        interrupt::free(|cs| self.value.borrow(cs).borrow_mut().take())
+    }
+
+    /// Moves the value into `local` on the first call after it was sent
+    ///
+    /// Meant for a `static mut` local of the interrupt handler: after the
+    /// handoff the handler accesses its own copy without a critical section.
+    pub fn receive<'a>(&self, local: &'a mut Option<T>) -> Option<&'a mut T> {
+        if local.is_none() {
+            *local = self.take();
+        }
+        local.as_mut()
+    }
+
+    /// Returns true once a value was sent, whether or not it was taken
+    pub fn is_sent(&self) -> bool {
+        interrupt::free(|cs| self.state.borrow(cs).get() == State::Sent)
+    }
+}
+
+impl<T> Default for Channel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Sender<'_, T> {
+    /// Sends `value` into the channel
+    pub fn send(self, value: T) {
+        interrupt::free(|cs| {
+            *self.channel.value.borrow(cs).borrow_mut() = Some(value);
+            self.channel.state.borrow(cs).set(State::Sent);
+        })
+    }
+}
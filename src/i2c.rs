@@ -1,33 +1,106 @@
-use core::ops::Deref;
+use core::{fmt, ops::Deref};
 
+use cortex_m::interrupt::CriticalSection;
 use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
 
 use crate::{
     gpio::*,
-    rcc::Rcc,
-    time::{KiloHertz, U32Ext},
+    rcc::{BusClock, Clocks, Rcc},
+    time::{Hertz, KiloHertz, U32Ext},
 };
 
 /// I2C abstraction
 pub struct I2c<I2C, SCLPIN, SDAPIN> {
     i2c: I2C,
     pins: (SCLPIN, SDAPIN),
+    timeout: Option<u32>,
 }
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// I2C peripheral
+///
+/// This allows writing code generic over all I2C peripherals.
+pub trait Instance:
+    sealed::Sealed + BusClock + Deref<Target = crate::pac::i2c1::RegisterBlock>
+{
+    /// Enables the clock of the peripheral
+    fn enable_clock(rcc: &mut Rcc);
+
+    /// Disables the clock of the peripheral
+    fn disable_clock(rcc: &mut Rcc);
+}
+
+/// I2C1 runs from the HSI, which is its reset default and never changed
+fn hsi_clock(_clocks: &Clocks) -> Hertz {
+    Hertz(8_000_000)
+}
+
+/// The other I2C instances always run from PCLK
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+fn pclk_clock(clocks: &Clocks) -> Hertz {
+    clocks.pclk()
+}
+
+/// Pin usable as SCL of `I2C`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as SCL of `{I2C}`",
+    note = "check that the pin provides this function and is set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait SclPin<I2C> {}
+/// Pin usable as SDA of `I2C`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as SDA of `{I2C}`",
+    note = "check that the pin provides this function and is set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait SdaPin<I2C> {}
 
+// Erased pins are vouched for by the `unsafe` constructor of `Unchecked`
+impl<I2C, AF> SclPin<I2C> for Unchecked<Pin<Alternate<AF>>> {}
+impl<I2C, AF> SclPin<I2C> for Unchecked<ErasedPin<Alternate<AF>>> {}
+impl<I2C, AF> SdaPin<I2C> for Unchecked<Pin<Alternate<AF>>> {}
+impl<I2C, AF> SdaPin<I2C> for Unchecked<ErasedPin<Alternate<AF>>> {}
+
 macro_rules! i2c_pins {
     ($($I2C:ident => {
-        scl => [$($scl:ty),+ $(,)*],
-        sda => [$($sda:ty),+ $(,)*],
+        scl => [$($sclport:ident::$scl:ident<Alternate<$sclaf:ident>>),+ $(,)*],
+        sda => [$($sdaport:ident::$sda:ident<Alternate<$sdaaf:ident>>),+ $(,)*],
     })+) => {
         $(
             $(
-                impl SclPin<crate::pac::$I2C> for $scl {}
+                impl SclPin<crate::pac::$I2C> for $sclport::$scl<Alternate<$sclaf>> {}
+
+                impl<MODE> ValidPin<crate::pac::$I2C, signal::Scl> for $sclport::$scl<MODE> {
+                    type Configured = $sclport::$scl<Alternate<$sclaf>>;
+
+                    fn setup(self, cs: &CriticalSection) -> Self::Configured {
+                        self.into_alternate(cs).set_open_drain(cs)
+                    }
+                }
             )+
             $(
-                impl SdaPin<crate::pac::$I2C> for $sda {}
+                impl SdaPin<crate::pac::$I2C> for $sdaport::$sda<Alternate<$sdaaf>> {}
+
+                impl<MODE> ValidPin<crate::pac::$I2C, signal::Sda> for $sdaport::$sda<MODE> {
+                    type Configured = $sdaport::$sda<Alternate<$sdaaf>>;
+
+                    fn setup(self, cs: &CriticalSection) -> Self::Configured {
+                        self.into_alternate(cs).set_open_drain(cs)
+                    }
+                }
             )+
         )+
     }
@@ -146,16 +219,41 @@ i2c_pins! {
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     OVERRUN,
     NACK,
     BUS,
+    /// The bus did not get ready within the timeout, see
+    /// [set_timeout](I2c::set_timeout)
+    TIMEOUT,
 }
 
 macro_rules! i2c {
-    ($($I2C:ident: ($i2c:ident, $i2cXen:ident, $i2cXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
+    ($($I2C:ident: ($i2c:ident, $i2c_setup:ident, $i2cXen:ident, $i2cXrst:ident, $apbenr:ident, $apbrstr:ident, $clock:ident),)+) => {
         $(
             use crate::pac::$I2C;
+
+            impl sealed::Sealed for $I2C {}
+
+            impl Instance for $I2C {
+                fn enable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$i2cXen().set_bit());
+                    clock_event!(enabled, stringify!($I2C));
+                }
+
+                fn disable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$i2cXen().clear_bit());
+                    clock_event!(disabled, stringify!($I2C));
+                }
+
+            }
+
+            impl BusClock for $I2C {
+                fn clock(clocks: &Clocks) -> Hertz {
+                    $clock(clocks)
+                }
+            }
             impl<SCLPIN, SDAPIN> I2c<$I2C, SCLPIN, SDAPIN> {
                 pub fn $i2c(i2c: $I2C, pins: (SCLPIN, SDAPIN), speed: KiloHertz, rcc: &mut Rcc) -> Self
                 where
@@ -164,11 +262,34 @@ macro_rules! i2c {
                 {
                     // Enable clock for I2C
                     rcc.regs.$apbenr.modify(|_, w| w.$i2cXen().set_bit());
+                    clock_event!(enabled, stringify!($I2C));
 
                     // Reset I2C
                     rcc.regs.$apbrstr.modify(|_, w| w.$i2cXrst().set_bit());
                     rcc.regs.$apbrstr.modify(|_, w| w.$i2cXrst().clear_bit());
-                    I2c { i2c, pins }.i2c_init(speed)
+                    let clock = <$I2C as BusClock>::clock(&rcc.clocks);
+                    I2c { i2c, pins, timeout: None }.i2c_init(speed, clock)
+                }
+
+                /// Creates a new I2C instance from pins in any mode
+                ///
+                /// The pins are switched to the alternate function of their signal in
+                /// open drain mode.
+                pub fn $i2c_setup<SCL, SDA>(
+                    i2c: $I2C,
+                    pins: (SCL, SDA),
+                    speed: KiloHertz,
+                    rcc: &mut Rcc,
+                    cs: &CriticalSection,
+                ) -> Self
+                where
+                    SCL: ValidPin<$I2C, signal::Scl, Configured = SCLPIN>,
+                    SDA: ValidPin<$I2C, signal::Sda, Configured = SDAPIN>,
+                    SCLPIN: SclPin<$I2C>,
+                    SDAPIN: SdaPin<$I2C>,
+                {
+                    let pins = (pins.0.setup(cs), pins.1.setup(cs));
+                    Self::$i2c(i2c, pins, speed, rcc)
                 }
             }
         )+
@@ -176,7 +297,7 @@ macro_rules! i2c {
 }
 
 i2c! {
-    I2C1: (i2c1, i2c1en, i2c1rst, apb1enr, apb1rstr),
+    I2C1: (i2c1, i2c1_setup, i2c1en, i2c1rst, apb1enr, apb1rstr, hsi_clock),
 }
 
 #[cfg(any(
@@ -192,7 +313,7 @@ i2c! {
     feature = "stm32f098",
 ))]
 i2c! {
-    I2C2: (i2c2, i2c2en, i2c2rst, apb1enr, apb1rstr),
+    I2C2: (i2c2, i2c2_setup, i2c2en, i2c2rst, apb1enr, apb1rstr, pclk_clock),
 }
 
 // It's s needed for the impls, but rustc doesn't recognize that
@@ -203,7 +324,7 @@ impl<I2C, SCLPIN, SDAPIN> I2c<I2C, SCLPIN, SDAPIN>
 where
     I2C: Deref<Target = I2cRegisterBlock>,
 {
-    fn i2c_init(self, speed: KiloHertz) -> Self {
+    fn i2c_init(self, speed: KiloHertz, clock: Hertz) -> Self {
         use core::cmp;
 
         // Make sure the I2C unit is disabled so we can configure it
@@ -216,23 +337,26 @@ where
         let sclh;
         let scll;
 
-        // We're using HSI here which runs at a fixed 8MHz
-        const FREQ: u32 = 8_000_000;
+        let freq = clock.0;
+        let speed_hz = speed.0 * 1_000;
 
-        // Normal I2C speeds use a different scaling than fast mode below
+        // Normal I2C speeds use a different scaling than fast mode below, the
+        // prescaled clock is 4MHz respectively 8MHz like in the RM0091 timing
+        // examples
         if speed <= 100_u32.khz() {
-            presc = 1;
-            scll = cmp::max((((FREQ >> presc) >> 1) / speed.0) - 1, 255) as u8;
-            sclh = scll - 4;
+            presc = cmp::min(freq.div_ceil(4_000_000), 16) - 1;
+            scll = cmp::min(((freq / (presc + 1)) >> 1) / speed_hz - 1, 255) as u8;
+            sclh = scll.saturating_sub(4);
             sdadel = 2;
             scldel = 4;
         } else {
-            presc = 0;
-            scll = cmp::max((((FREQ >> presc) >> 1) / speed.0) - 1, 255) as u8;
-            sclh = scll - 6;
+            presc = cmp::min(freq.div_ceil(8_000_000), 16) - 1;
+            scll = cmp::min(((freq / (presc + 1)) >> 1) / speed_hz - 1, 255) as u8;
+            sclh = scll.saturating_sub(6);
             sdadel = 1;
             scldel = 3;
         }
+        let presc = presc as u8;
 
         // Enable I2C signal generator, and configure I2C for configured speed
         self.i2c.timingr.write(|w| {
@@ -258,6 +382,115 @@ where
         (self.i2c, self.pins)
     }
 
+    /// Limits every wait for the bus to `polls` status register reads
+    ///
+    /// Without a limit, the default, a target holding SCL low blocks a
+    /// transfer forever. With a limit the transfer fails with
+    /// [Error::TIMEOUT] instead, so the watchdog is still fed, and the
+    /// peripheral is reset to be ready for the next transfer. A status read
+    /// takes a few cycles of the APB clock, one byte on a 100kHz bus is
+    /// about 90us.
+    pub fn set_timeout(&mut self, polls: Option<u32>) {
+        self.timeout = polls;
+    }
+
+    /// Polls the status until `ready` returns true or an error flag is set
+    fn wait_for<F>(&self, ready: F) -> Result<(), Error>
+    where
+        F: Fn(&crate::stm32::i2c1::isr::R) -> bool,
+    {
+        let mut polls = self.timeout;
+        loop {
+            let isr = self.i2c.isr.read();
+            self.check_and_clear_error_flags(&isr)?;
+            if ready(&isr) {
+                return Ok(());
+            }
+            if let Some(remaining) = polls.as_mut() {
+                if *remaining == 0 {
+                    self.software_reset();
+                    return Err(Error::TIMEOUT);
+                }
+                *remaining -= 1;
+            }
+        }
+    }
+
+    /// Aborts a stuck transfer and returns the peripheral to idle
+    ///
+    /// PE has to stay cleared for at least 3 APB clock cycles, every read of
+    /// CR1 takes at least one of them.
+    fn software_reset(&self) {
+        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        for _ in 0..3 {
+            let _ = self.i2c.cr1.read();
+        }
+        self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+    }
+
+    /// Writes the state of the I2C registers in human readable form
+    ///
+    /// Reading the registers has no side effects, so this can be used to
+    /// collect information for bug reports at any time.
+    pub fn debug_dump<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        crate::dump::register(
+            w,
+            "CR1",
+            self.i2c.cr1.read().bits(),
+            &[
+                (0, "PE"),
+                (1, "TXIE"),
+                (2, "RXIE"),
+                (3, "ADDRIE"),
+                (4, "NACKIE"),
+                (5, "STOPIE"),
+                (6, "TCIE"),
+                (7, "ERRIE"),
+                (12, "ANFOFF"),
+                (14, "TXDMAEN"),
+                (15, "RXDMAEN"),
+                (16, "SBC"),
+                (17, "NOSTRETCH"),
+            ],
+        )?;
+        crate::dump::register(
+            w,
+            "CR2",
+            self.i2c.cr2.read().bits(),
+            &[
+                (10, "RD_WRN"),
+                (11, "ADD10"),
+                (13, "START"),
+                (14, "STOP"),
+                (15, "NACK"),
+                (24, "RELOAD"),
+                (25, "AUTOEND"),
+            ],
+        )?;
+        crate::dump::register(w, "TIMINGR", self.i2c.timingr.read().bits(), &[])?;
+        crate::dump::register(
+            w,
+            "ISR",
+            self.i2c.isr.read().bits(),
+            &[
+                (0, "TXE"),
+                (1, "TXIS"),
+                (2, "RXNE"),
+                (3, "ADDR"),
+                (4, "NACKF"),
+                (5, "STOPF"),
+                (6, "TC"),
+                (7, "TCR"),
+                (8, "BERR"),
+                (9, "ARLO"),
+                (10, "OVR"),
+                (12, "TIMEOUT"),
+                (15, "BUSY"),
+                (16, "DIR"),
+            ],
+        )
+    }
+
     fn check_and_clear_error_flags(&self, isr: &crate::stm32::i2c1::isr::R) -> Result<(), Error> {
         // If we have a set overrun flag, clear it and return an OVERRUN error
         if isr.ovr().bit_is_set() {
@@ -286,13 +519,7 @@ where
 
     fn send_byte(&self, byte: u8) -> Result<(), Error> {
         // Wait until we're ready for sending
-        loop {
-            let isr = self.i2c.isr.read();
-            self.check_and_clear_error_flags(&isr)?;
-            if isr.txis().bit_is_set() {
-                break;
-            }
-        }
+        self.wait_for(|isr| isr.txis().bit_is_set())?;
 
         // Push out a byte of data
         self.i2c.txdr.write(|w| unsafe { w.bits(u32::from(byte)) });
@@ -302,13 +529,7 @@ where
     }
 
     fn recv_byte(&self) -> Result<u8, Error> {
-        loop {
-            let isr = self.i2c.isr.read();
-            self.check_and_clear_error_flags(&isr)?;
-            if isr.rxne().bit_is_set() {
-                break;
-            }
-        }
+        self.wait_for(|isr| isr.rxne().bit_is_set())?;
 
         let value = self.i2c.rxdr.read().bits() as u8;
         Ok(value)
@@ -338,13 +559,7 @@ where
         self.i2c.cr2.modify(|_, w| w.start().set_bit());
 
         // Wait until the transmit buffer is empty and there hasn't been any error condition
-        loop {
-            let isr = self.i2c.isr.read();
-            self.check_and_clear_error_flags(&isr)?;
-            if isr.txis().bit_is_set() || isr.tc().bit_is_set() {
-                break;
-            }
-        }
+        self.wait_for(|isr| isr.txis().bit_is_set() || isr.tc().bit_is_set())?;
 
         // Send out all individual bytes
         for c in bytes {
@@ -352,13 +567,7 @@ where
         }
 
         // Wait until data was sent
-        loop {
-            let isr = self.i2c.isr.read();
-            self.check_and_clear_error_flags(&isr)?;
-            if isr.tc().bit_is_set() {
-                break;
-            }
-        }
+        self.wait_for(|isr| isr.tc().bit_is_set())?;
 
         // Set up current address for reading
         self.i2c.cr2.modify(|_, w| {
@@ -27,8 +27,22 @@ pub use stm32f0::stm32f0x2 as pac;
 ))]
 pub use stm32f0::stm32f0x8 as pac;
 
+/// Records a peripheral clock transition with the `power-profiling` feature
+macro_rules! clock_event {
+    ($event:ident, $name:expr) => {
+        #[cfg(feature = "power-profiling")]
+        crate::profile::$event($name);
+    };
+}
+
 #[cfg(feature = "device-selected")]
 pub mod adc;
+#[cfg(any(feature = "board-nucleo-f042k6", feature = "board-stm32f072b-disco"))]
+pub mod boards;
+#[cfg(feature = "device-selected")]
+pub mod bridge;
+#[cfg(feature = "device-selected")]
+pub mod capture;
 #[cfg(any(
     feature = "stm32f051",
     feature = "stm32f071",
@@ -41,13 +55,38 @@ pub mod dac;
 #[cfg(feature = "device-selected")]
 pub mod delay;
 #[cfg(feature = "device-selected")]
+mod dump;
+#[cfg(feature = "device-selected")]
+pub mod fan;
+#[cfg(feature = "device-selected")]
 pub mod flash;
 #[cfg(feature = "device-selected")]
 pub mod gpio;
 #[cfg(feature = "device-selected")]
+pub mod handoff;
+#[cfg(feature = "device-selected")]
 pub mod i2c;
+#[cfg(all(
+    feature = "rtic",
+    any(
+        feature = "stm32f031",
+        feature = "stm32f038",
+        feature = "stm32f042",
+        feature = "stm32f048",
+        feature = "stm32f051",
+        feature = "stm32f058",
+        feature = "stm32f071",
+        feature = "stm32f072",
+        feature = "stm32f078",
+        feature = "stm32f091",
+        feature = "stm32f098",
+    )
+))]
+pub mod monotonic;
 #[cfg(feature = "device-selected")]
 pub mod prelude;
+#[cfg(all(feature = "device-selected", feature = "power-profiling"))]
+pub mod profile;
 #[cfg(feature = "device-selected")]
 pub mod pwm;
 #[cfg(feature = "device-selected")]
@@ -62,6 +101,8 @@ pub mod spi;
 pub mod time;
 #[cfg(feature = "device-selected")]
 pub mod timers;
+#[cfg(feature = "device-selected")]
+pub mod trace;
 #[cfg(any(
     feature = "stm32f031",
     feature = "stm32f051",
@@ -0,0 +1,102 @@
+//! RTIC monotonic timer backed by the 32-bit TIM2
+//!
+//! Enabled with the `rtic` feature. The counter of TIM2 runs freely at
+//! `FREQ` Hz and channel 1 is used to schedule the next task.
+//!
+//! # Example
+//! ``` ignore
+//! use stm32f0xx_hal::monotonic::Tim2Monotonic;
+//!
+//! #[rtic::app(device = stm32f0xx_hal::pac, dispatchers = [USART1])]
+//! mod app {
+//!     use super::*;
+//!
+//!     // 1 MHz resolution, wraps around after about 71 minutes
+//!     #[monotonic(binds = TIM2, default = true)]
+//!     type Mono = Tim2Monotonic<1_000_000>;
+//!
+//!     #[init]
+//!     fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+//!         let mut dp = cx.device;
+//!         let mut rcc = dp.RCC.configure().sysclk(48.mhz()).freeze(&mut dp.FLASH);
+//!         let mono = Tim2Monotonic::new(dp.TIM2, &mut rcc);
+//!         (Shared {}, Local {}, init::Monotonics(mono))
+//!     }
+//! }
+//! ```
+
+use rtic_monotonic::Monotonic;
+
+use crate::pac::TIM2;
+use crate::rcc::Rcc;
+
+/// TIM2 as RTIC monotonic with a tick rate of `FREQ` Hz
+pub struct Tim2Monotonic<const FREQ: u32> {
+    tim: TIM2,
+}
+
+impl<const FREQ: u32> Tim2Monotonic<FREQ> {
+    /// Configures TIM2 as free running counter with `FREQ` Hz
+    ///
+    /// Panics if `FREQ` cannot be derived exactly from the timer clock.
+    pub fn new(tim: TIM2, rcc: &mut Rcc) -> Self {
+        // enable and reset peripheral to a clean slate state
+        rcc.regs.apb1enr.modify(|_, w| w.tim2en().set_bit());
+        clock_event!(enabled, "TIM2");
+        rcc.regs.apb1rstr.modify(|_, w| w.tim2rst().set_bit());
+        rcc.regs.apb1rstr.modify(|_, w| w.tim2rst().clear_bit());
+
+        let timclk = rcc.clocks.timclk().0;
+        assert!(FREQ > 0 && timclk % FREQ == 0);
+        let psc = cast::u16(timclk / FREQ - 1).unwrap();
+        tim.psc.write(|w| w.psc().bits(psc));
+        tim.arr.write(|w| unsafe { w.bits(u32::MAX) });
+
+        // Load the prescaler without raising an update interrupt
+        tim.cr1.modify(|_, w| w.urs().set_bit());
+        tim.egr.write(|w| w.ug().set_bit());
+        tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        Tim2Monotonic { tim }
+    }
+
+    /// Releases the TIM peripheral
+    pub fn release(self) -> TIM2 {
+        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim.dier.reset();
+        self.tim
+    }
+}
+
+impl<const FREQ: u32> Monotonic for Tim2Monotonic<FREQ> {
+    type Instant = fugit::TimerInstantU32<FREQ>;
+    type Duration = fugit::TimerDurationU32<FREQ>;
+
+    fn now(&mut self) -> Self::Instant {
+        Self::Instant::from_ticks(self.tim.cnt.read().bits())
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        let ticks = instant.duration_since_epoch().ticks();
+        self.tim.ccr1().write(|w| unsafe { w.bits(ticks) });
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.tim.sr.modify(|_, w| w.cc1if().clear_bit());
+    }
+
+    fn zero() -> Self::Instant {
+        Self::Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        // Restart the counter from 0 and interrupt on compare matches
+        self.tim.egr.write(|w| w.ug().set_bit());
+        self.tim
+            .sr
+            .modify(|_, w| w.uif().clear_bit().cc1if().clear_bit());
+        self.tim.dier.modify(|_, w| w.cc1ie().set_bit());
+    }
+}
@@ -0,0 +1,174 @@
+//! Peripheral clock profiling
+//!
+//! With the `power-profiling` feature the HAL records every time it gates or
+//! ungates the clock of a peripheral. Together with a timestamp source this
+//! shows which peripherals keep their clocks running, e.g. because they are
+//! never released.
+//!
+//! # Example
+//! ``` no_run
+//! use stm32f0xx_hal as hal;
+//!
+//! use crate::hal::pac;
+//! use crate::hal::profile;
+//!
+//! // Timestamp transitions with the free running counter of TIM2
+//! profile::set_time_source(|| unsafe { (*pac::TIM2::ptr()).cnt.read().bits() });
+//!
+//! // ... run the application ...
+//!
+//! profile::for_each(|name, stats| {
+//!     let _ = (name, stats.enables, stats.running);
+//! });
+//! ```
+
+use core::cell::RefCell;
+
+use cortex_m::interrupt::{self, Mutex};
+
+/// Maximum number of peripherals that are tracked
+const MAX_PERIPHERALS: usize = 24;
+
+/// Clock statistics of a single peripheral
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of times the clock was enabled
+    pub enables: u32,
+    /// Number of times the clock was disabled
+    pub disables: u32,
+    /// Time spent with the clock running, in ticks of the time source
+    pub running: u32,
+    /// Time spent with the clock gated after it was first used, in ticks of the
+    /// time source
+    pub gated: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    name: &'static str,
+    stats: Stats,
+    running: bool,
+    since: u32,
+}
+
+struct Profile {
+    now: Option<fn() -> u32>,
+    entries: [Option<Entry>; MAX_PERIPHERALS],
+}
+
+static PROFILE: Mutex<RefCell<Profile>> = Mutex::new(RefCell::new(Profile {
+    now: None,
+    entries: [None; MAX_PERIPHERALS],
+}));
+
+impl Profile {
+    fn now(&self) -> u32 {
+        self.now.map(|now| now()).unwrap_or(0)
+    }
+
+    fn entry(&mut self, name: &'static str) -> Option<&mut Entry> {
+        let now = self.now();
+        let index = self
+            .entries
+            .iter()
+            .position(|e| matches!(e, Some(e) if e.name == name))
+            .or_else(|| self.entries.iter().position(Option::is_none))?;
+        Some(self.entries[index].get_or_insert(Entry {
+            name,
+            stats: Stats::default(),
+            running: false,
+            since: now,
+        }))
+    }
+
+    fn transition(&mut self, name: &'static str, running: bool) {
+        let now = self.now();
+        if let Some(entry) = self.entry(name) {
+            entry.update(now);
+            if running {
+                entry.stats.enables += 1;
+            } else {
+                entry.stats.disables += 1;
+            }
+            entry.running = running;
+        }
+    }
+}
+
+impl Entry {
+    /// Accounts the time since the last update
+    fn update(&mut self, now: u32) {
+        let elapsed = now.wrapping_sub(self.since);
+        if self.running {
+            self.stats.running = self.stats.running.wrapping_add(elapsed);
+        } else {
+            self.stats.gated = self.stats.gated.wrapping_add(elapsed);
+        }
+        self.since = now;
+    }
+}
+
+/// Sets the function timestamping clock transitions
+///
+/// The source has to count up and may wrap around at `u32::MAX`. Without a time
+/// source only the transitions are counted.
+pub fn set_time_source(now: fn() -> u32) {
+    interrupt::free(|cs| PROFILE.borrow(cs).borrow_mut().now = Some(now));
+}
+
+/// Returns the statistics of the peripheral `name`, e.g. `"TIM3"`
+pub fn stats(name: &str) -> Option<Stats> {
+    interrupt::free(|cs| {
+        let mut profile = PROFILE.borrow(cs).borrow_mut();
+        let now = profile.now();
+        profile.entries.iter_mut().flatten().find_map(|entry| {
+            if entry.name == name {
+                entry.update(now);
+                Some(entry.stats)
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// Calls `f` with the statistics of every peripheral seen so far
+pub fn for_each<F>(mut f: F)
+where
+    F: FnMut(&'static str, Stats),
+{
+    let mut entries = [None; MAX_PERIPHERALS];
+    interrupt::free(|cs| {
+        let mut profile = PROFILE.borrow(cs).borrow_mut();
+        let now = profile.now();
+        for entry in profile.entries.iter_mut().flatten() {
+            entry.update(now);
+        }
+        entries = profile.entries;
+    });
+    for entry in entries.iter().flatten() {
+        f(entry.name, entry.stats);
+    }
+}
+
+/// Resets all statistics
+pub fn reset() {
+    interrupt::free(|cs| {
+        let mut profile = PROFILE.borrow(cs).borrow_mut();
+        let now = profile.now();
+        for entry in profile.entries.iter_mut().flatten() {
+            entry.stats = Stats::default();
+            entry.since = now;
+        }
+    });
+}
+
+/// Records that the clock of `name` was enabled
+pub(crate) fn enabled(name: &'static str) {
+    interrupt::free(|cs| PROFILE.borrow(cs).borrow_mut().transition(name, true));
+}
+
+/// Records that the clock of `name` was disabled
+pub(crate) fn disabled(name: &'static str) {
+    interrupt::free(|cs| PROFILE.borrow(cs).borrow_mut().transition(name, false));
+}
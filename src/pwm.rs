@@ -3,8 +3,9 @@ use core::{marker::PhantomData, mem::MaybeUninit};
 
 use crate::rcc::Rcc;
 
-use crate::time::Hertz;
+use crate::time::{Hertz, MicroSecond};
 use embedded_hal as hal;
+use embedded_hal::timer::{CountDown, Periodic};
 
 pub trait Pins<TIM, P> {
     const C1: bool = false;
@@ -16,6 +17,7 @@ pub trait Pins<TIM, P> {
     const C4: bool = false;
     type Channels;
 }
+use crate::timers::PinBkin;
 use crate::timers::PinC1;
 use crate::timers::PinC1N;
 use crate::timers::PinC2;
@@ -23,6 +25,7 @@ use crate::timers::PinC2N;
 use crate::timers::PinC3;
 use crate::timers::PinC3N;
 use crate::timers::PinC4;
+use crate::timers::{OnePulse, OnePulseTimer};
 
 pub struct C1;
 pub struct C1N;
@@ -37,6 +40,239 @@ pub struct PwmChannels<TIM, CHANNELS> {
     _tim: PhantomData<TIM>,
 }
 
+impl<TIM, CHANNELS> PwmChannels<TIM, CHANNELS> {
+    /// Creates the channels without configuring the timer, e.g. for `static` items
+    ///
+    /// # Safety
+    ///
+    /// The timer must already be configured for PWM on these channels and the
+    /// channels must not be in use anywhere else.
+    pub const unsafe fn new_unchecked() -> Self {
+        PwmChannels {
+            _channel: PhantomData,
+            _tim: PhantomData,
+        }
+    }
+}
+
+impl<TIM, CHANNELS> PwmChannels<TIM, CHANNELS>
+where
+    Self: hal::PwmPin<Duty = u16>,
+{
+    /// Sets the duty to `percent` of the period, values above 100 are clamped
+    ///
+    /// The duty is computed from the current period and rounded to the
+    /// nearest counter tick.
+    pub fn set_duty_percent(&mut self, percent: u8) {
+        self.set_duty_fraction(u32::from(percent.min(100)), 100);
+    }
+
+    /// Sets the duty to `num / denom` of the period, fractions above 1 are
+    /// clamped
+    ///
+    /// The duty is computed from the current period and rounded to the
+    /// nearest counter tick. Panics if `denom` is 0.
+    pub fn set_duty_fraction(&mut self, num: u32, denom: u32) {
+        assert!(denom > 0);
+        let max = u64::from(hal::PwmPin::get_max_duty(self));
+        let num = u64::from(num.min(denom));
+        let denom = u64::from(denom);
+        let duty = (max * num + denom / 2) / denom;
+        hal::PwmPin::set_duty(self, duty as u16);
+    }
+}
+
+/// Channel of a PWM timer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channel {
+    C1,
+    C2,
+    C3,
+    C4,
+}
+
+/// Handle to a PWM timer for operations spanning several channels
+///
+/// Implements the `embedded_hal::Pwm` trait with the period in timer clock
+/// cycles.
+///
+/// The handle borrows the channel it was created from, so it can't outlive the
+/// channels when the timer is released.
+pub struct Pwm<'a, TIM> {
+    _tim: PhantomData<&'a TIM>,
+}
+
+impl<TIM, CHANNELS> PwmChannels<TIM, CHANNELS> {
+    /// Returns a handle to the timer driving the channel
+    pub fn timer(&self) -> Pwm<'_, TIM> {
+        Pwm { _tim: PhantomData }
+    }
+}
+
+/// PWM configuration owning the timer and its pins
+///
+/// Created by the `PwmTimer` constructors, which return the channels like the
+/// constructor functions. [release](PwmTimer::release) gives back the timer and
+/// the pins.
+pub struct PwmTimer<TIM, PINS> {
+    tim: TIM,
+    pins: PINS,
+}
+
+/// Runtime control of the PWM period
+pub trait Period {
+    /// Returns the PWM period in timer clock cycles
+    fn period(&self) -> u32;
+
+    /// Changes the PWM period to `cycles` timer clock cycles
+    ///
+    /// The new period starts with the next update event, the running period is
+    /// completed. Duties are not scaled, they keep their value in counter ticks.
+    /// Periods that are no multiple of the prescaler are rounded down, the
+    /// others are read back unchanged by [period](Period::period). Panics if
+    /// `cycles` is 0.
+    fn set_period(&mut self, cycles: u32);
+}
+
+// Updates of several channels committed on the same update event
+macro_rules! pwm_timer {
+    ($($TIMX:ident: [$($C:ident => $ccrx:ident),+],)+) => {
+        $(
+            impl Pwm<'_, $TIMX> {
+                /// Sets the duty of several channels at once
+                ///
+                /// The compare registers are preloaded, so all new duties take effect
+                /// together at the next update event and no period uses a mix of old
+                /// and new values. Panics if a channel does not exist on the timer.
+                #[allow(unused_unsafe)]
+                pub fn set_duties(&mut self, duties: &[(Channel, u16)]) {
+                    //NOTE(unsafe) the timer is configured for PWM and only the
+                    //compare registers and UDIS are touched
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // Hold back the transfer of the preloaded values until all are written
+                    tim.cr1.modify(|_, w| w.udis().set_bit());
+                    for &(channel, duty) in duties {
+                        match channel {
+                            $(Channel::$C => tim.$ccrx().write(|w| unsafe { w.ccr().bits(duty.into()) }),)+
+                            #[allow(unreachable_patterns)]
+                            _ => panic!("channel not available on this timer"),
+                        }
+                    }
+                    tim.cr1.modify(|_, w| w.udis().clear_bit());
+                }
+
+                /// Shifts the toggle events of `lag` by `shift` ticks against `lead`
+                ///
+                /// Both channels have to be in [OutputMode::Toggle], e.g. for the two
+                /// legs of a phase shifted full bridge. A shift of half the period
+                /// gives a phase of 90 degrees of the square waves. Set the channels
+                /// to [OutputMode::ForceInactive] before switching to toggle mode, so
+                /// both start from the same level.
+                pub fn set_phase_shift(&mut self, lead: Channel, lag: Channel, shift: u16) {
+                    //NOTE(unsafe) atomic read with no side effects
+                    let period = unsafe { u32((*$TIMX::ptr()).arr.read().arr().bits() as u16) } + 1;
+                    let shift = (u32(shift) % period) as u16;
+                    self.set_duties(&[(lead, 0), (lag, shift)]);
+                }
+            }
+
+            impl hal::Pwm for Pwm<'_, $TIMX> {
+                type Channel = Channel;
+                /// Period in timer clock cycles, see [Period]
+                type Time = u32;
+                type Duty = u16;
+
+                fn disable(&mut self, channel: Channel) {
+                    //NOTE(unsafe) only the enable bit of the channel is modified
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let bit = Self::ccer_enable_bit(channel);
+                    tim.ccer.modify(|r, w| unsafe { w.bits(r.bits() & !bit) });
+                }
+
+                fn enable(&mut self, channel: Channel) {
+                    //NOTE(unsafe) only the enable bit of the channel is modified
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let bit = Self::ccer_enable_bit(channel);
+                    tim.ccer.modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+                }
+
+                fn get_period(&self) -> u32 {
+                    Period::period(self)
+                }
+
+                fn get_duty(&self, channel: Channel) -> u16 {
+                    //NOTE(unsafe) atomic read with no side effects
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    match channel {
+                        $(Channel::$C => tim.$ccrx().read().ccr().bits() as u16,)+
+                        #[allow(unreachable_patterns)]
+                        _ => panic!("channel not available on this timer"),
+                    }
+                }
+
+                fn get_max_duty(&self) -> u16 {
+                    //NOTE(unsafe) atomic read with no side effects
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.arr.read().arr().bits() as u16
+                }
+
+                fn set_duty(&mut self, channel: Channel, duty: u16) {
+                    self.set_duties(&[(channel, duty)]);
+                }
+
+                fn set_period<P>(&mut self, period: P)
+                where
+                    P: Into<u32>,
+                {
+                    Period::set_period(self, period.into());
+                }
+            }
+
+            impl Pwm<'_, $TIMX> {
+                fn ccer_enable_bit(channel: Channel) -> u32 {
+                    match channel {
+                        $(Channel::$C)|+ => 1 << (4 * channel as u32),
+                        #[allow(unreachable_patterns)]
+                        _ => panic!("channel not available on this timer"),
+                    }
+                }
+            }
+
+            impl Period for Pwm<'_, $TIMX> {
+                fn period(&self) -> u32 {
+                    //NOTE(unsafe) atomic read with no side effects
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let psc = u32(tim.psc.read().psc().bits()) + 1;
+                    let arr = u32(tim.arr.read().arr().bits() as u16) + 1;
+                    psc.saturating_mul(arr)
+                }
+
+                fn set_period(&mut self, cycles: u32) {
+                    //NOTE(unsafe) PSC and ARR are preloaded and only written through the
+                    //timer handle
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    assert!(cycles > 0);
+                    let psc = u16((cycles - 1) / (1 << 16)).unwrap();
+                    // The counter runs from 0 to ARR, so a period has ARR + 1 ticks
+                    let arr = u16(cycles / u32(psc + 1) - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+                }
+            }
+        )+
+    };
+}
+
+/// Output polarity of a PWM channel, or active level of a break input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Polarity {
+    /// Output is high while active
+    ActiveHigh,
+    /// Output is low while active
+    ActiveLow,
+}
+
 macro_rules! pins_impl {
     ( $( ( $($PINX:ident),+ ), ( $($TRAIT:ident),+ ), ( $($ENCHX:ident),* ); )+ ) => {
         $(
@@ -56,6 +292,7 @@ pins_impl!(
     (P1, P2, P3, P4), (PinC1, PinC2, PinC3, PinC4), (C1, C2, C3, C4);
     (P1, P1N, P2, P2N, P3, P3N), (PinC1, PinC1N, PinC2, PinC2N, PinC3, PinC3N), (C1, C1N, C2, C2N, C3, C3N);
     (P1, P1N, P2, P2N), (PinC1, PinC1N, PinC2, PinC2N), (C1, C1N, C2, C2N);
+    (P1, P1N, P2), (PinC1, PinC1N, PinC2), (C1, C1N, C2);
     (P2, P2N, P3, P3N), (PinC2, PinC2N, PinC3, PinC3N), (C2, C2N, C3, C3N);
     (P1, P1N, P3, P3N), (PinC1, PinC1N, PinC3, PinC3N), (C1, C1N, C3, C3N);
     (P2, P3, P4), (PinC2, PinC3, PinC4), (C2, C3, C4);
@@ -107,7 +344,8 @@ impl<TIM, P1: PinC4<TIM>, P2: PinC4<TIM>, P3: PinC4<TIM>, P4: PinC4<TIM>> PinC4<
 {
 }
 
-// the following timer have a main output switch, enable the automatic output
+// the following timer have a main output switch, enable the automatic output by
+// default, see `Pwm::set_output_enable`
 macro_rules! brk {
     (TIM1, $tim:ident) => {
         $tim.bdtr.modify(|_, w| w.aoe().set_bit());
@@ -124,73 +362,438 @@ macro_rules! brk {
     ($_other:ident, $_tim:ident) => {};
 }
 
+// the following timer have a main output switch, outputs are only driven while MOE is set
+macro_rules! moe {
+    (TIM1, $tim:ident) => {
+        $tim.bdtr.read().moe().bit_is_set()
+    };
+    (TIM15, $tim:ident) => {
+        $tim.bdtr.read().moe().bit_is_set()
+    };
+    (TIM16, $tim:ident) => {
+        $tim.bdtr.read().moe().bit_is_set()
+    };
+    (TIM17, $tim:ident) => {
+        $tim.bdtr.read().moe().bit_is_set()
+    };
+    ($_other:ident, $_tim:ident) => {
+        true
+    };
+}
+
+// Read-back of the channel configuration
+macro_rules! channel_state {
+    ($TIMX:ident, $C:ident, $ccxe:ident, $ccxp:ident) => {
+        impl PwmChannels<$TIMX, $C> {
+            /// Returns true if the channel output is enabled
+            ///
+            /// On timers with a main output switch this also requires MOE to
+            /// be set, so it turns false after a break event forced the
+            /// outputs off.
+            pub fn is_enabled(&self) -> bool {
+                //NOTE(unsafe) atomic read with no side effects
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.ccer.read().$ccxe().bit_is_set() && moe!($TIMX, tim)
+            }
+
+            /// Returns the output polarity of the channel
+            pub fn polarity(&self) -> Polarity {
+                //NOTE(unsafe) atomic read with no side effects
+                let tim = unsafe { &*$TIMX::ptr() };
+                if tim.ccer.read().$ccxp().bit_is_set() {
+                    Polarity::ActiveLow
+                } else {
+                    Polarity::ActiveHigh
+                }
+            }
+
+            /// Sets the output polarity of the channel
+            ///
+            /// The duty keeps its meaning, it is the time the output is active.
+            /// Complementary outputs have their own polarity, so inverted
+            /// gate drivers can be matched per output.
+            pub fn set_polarity(&mut self, polarity: Polarity) {
+                //NOTE(unsafe) atomic write with no side effects
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.ccer
+                    .modify(|_, w| w.$ccxp().bit(polarity == Polarity::ActiveLow));
+            }
+
+            /// Returns the PWM period in timer clock cycles
+            pub fn period(&self) -> u32 {
+                //NOTE(unsafe) atomic read with no side effects
+                let tim = unsafe { &*$TIMX::ptr() };
+                let psc = u32(tim.psc.read().psc().bits()) + 1;
+                // Raw read, the reserved upper half of 16 bit timers reads as 0
+                let arr = tim.arr.read().bits().saturating_add(1);
+                psc.saturating_mul(arr)
+            }
+        }
+    };
+}
+
+/// Output compare mode of a channel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode {
+    /// Active while the counter is below the duty, the default
+    PwmMode1,
+    /// Inactive while the counter is below the duty
+    PwmMode2,
+    /// Output forced to its active level
+    ForceActive,
+    /// Output forced to its inactive level
+    ForceInactive,
+    /// Output toggles when the counter matches the duty
+    ///
+    /// This gives a 50% square wave at half the PWM frequency. Two channels
+    /// in this mode are phase shifted by the difference of their duties, see
+    /// [Pwm::set_phase_shift]. The combined PWM modes of newer STM32 timers do
+    /// not exist on the F0.
+    Toggle,
+}
+
+impl OutputMode {
+    fn bits(self) -> u32 {
+        match self {
+            OutputMode::PwmMode1 => 0b110,
+            OutputMode::PwmMode2 => 0b111,
+            OutputMode::ForceActive => 0b101,
+            OutputMode::ForceInactive => 0b100,
+            OutputMode::Toggle => 0b011,
+        }
+    }
+}
+
+// CCMRx output compare mode field, shifted by 4 or 12 for the channel
+const CCMR_OCM_MASK: u32 = 0b111;
+
+// Selection of the output compare mode of channels with an OCxM field
+macro_rules! output_mode {
+    ($TIMX:ident, C1) => {
+        output_mode!($TIMX, C1, ccmr1_output, 4);
+    };
+    ($TIMX:ident, C2) => {
+        output_mode!($TIMX, C2, ccmr1_output, 12);
+    };
+    ($TIMX:ident, C3) => {
+        output_mode!($TIMX, C3, ccmr2_output, 4);
+    };
+    ($TIMX:ident, C4) => {
+        output_mode!($TIMX, C4, ccmr2_output, 12);
+    };
+    ($TIMX:ident, $C:ident, $ccmrx:ident, $shift:expr) => {
+        impl PwmChannels<$TIMX, $C> {
+            /// Selects the output compare mode of the channel
+            ///
+            /// The forced modes park the output at a defined level independent
+            /// of the counter, the duty is kept for switching back to PWM. A
+            /// complementary output follows the mode of its channel.
+            pub fn set_output_mode(&mut self, mode: OutputMode) {
+                //NOTE(unsafe) only the mode of this channel is modified
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.$ccmrx().modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(CCMR_OCM_MASK << $shift)) | (mode.bits() << $shift))
+                });
+            }
+        }
+    };
+}
+
+/// Re-enabling of the outputs after a break event
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputEnable {
+    /// The outputs are enabled again at the next update event once the break
+    /// input is inactive, the PWM constructors select this mode
+    Automatic,
+    /// The outputs stay off until the fault is cleared in software
+    Manual,
+}
+
+/// Level of an output while the main output enable is cleared
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IdleState {
+    Low,
+    High,
+}
+
+// CR2 output idle state bit of channel 1, the next channels are shifted by 2
+const CR2_OIS_SHIFT: u32 = 8;
+
+/// Break configuration of a timer with a main output switch
+///
+/// The default is an active low break input with [OutputEnable::Automatic],
+/// the output enable mode the PWM constructors select.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BreakConfig {
+    /// Active level of the break input
+    pub polarity: Polarity,
+    /// Re-enabling of the outputs after a break event
+    pub output_enable: OutputEnable,
+}
+
+impl Default for BreakConfig {
+    fn default() -> Self {
+        BreakConfig {
+            polarity: Polarity::ActiveLow,
+            output_enable: OutputEnable::Automatic,
+        }
+    }
+}
+
+// Break input of the timers with a main output switch
+macro_rules! pwm_break {
+    ($($TIMX:ident: [$($C:ident),+], [$($CN:ident),+],)+) => {
+        $(
+            impl Pwm<'_, $TIMX> {
+                /// Enables the break input on `pin`
+                ///
+                /// While the input is at its active level the outputs are disabled in
+                /// hardware, e.g. on a fault signal of a gate driver. The break input
+                /// has no digital filter on the F0, glitches have to be suppressed
+                /// externally.
+                pub fn enable_break<PIN>(&mut self, _pin: PIN, polarity: Polarity)
+                where
+                    PIN: PinBkin<$TIMX>,
+                {
+                    //NOTE(unsafe) BDTR is only written through the timer handle
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.modify(|_, w| {
+                        w.bkp()
+                            .bit(polarity == Polarity::ActiveHigh)
+                            .bke()
+                            .set_bit()
+                    });
+                }
+
+                /// Enables the break input on `pin` and applies `config`
+                ///
+                /// With [OutputEnable::Manual] a fault latches the outputs off until
+                /// [clear_fault](PwmChannels::clear_fault) is called.
+                ///
+                /// The LOCK bits of BDTR can only be written once after reset and the
+                /// PWM constructors already write BDTR, so the break configuration
+                /// can't be write protected and stays changeable.
+                pub fn configure_break<PIN>(&mut self, pin: PIN, config: BreakConfig)
+                where
+                    PIN: PinBkin<$TIMX>,
+                {
+                    self.set_output_enable(config.output_enable);
+                    self.enable_break(pin, config.polarity);
+                }
+
+                /// Selects how the outputs are re-enabled after a break event
+                pub fn set_output_enable(&mut self, mode: OutputEnable) {
+                    //NOTE(unsafe) BDTR is only written through the timer handle
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr
+                        .modify(|_, w| w.aoe().bit(mode == OutputEnable::Automatic));
+                }
+
+                /// Enables all outputs of the timer by setting the main output enable
+                ///
+                /// The channels keep their individual enables, this switches all of
+                /// them at once.
+                pub fn enable_outputs(&mut self) {
+                    //NOTE(unsafe) BDTR is only written through the timer handle
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.modify(|_, w| w.moe().set_bit());
+                }
+
+                /// Disables all outputs of the timer at once
+                ///
+                /// The outputs take their idle levels. With automatic output enable
+                /// they are enabled again at the next update event, select
+                /// [OutputEnable::Manual] to keep them off.
+                pub fn disable_outputs(&mut self) {
+                    //NOTE(unsafe) BDTR is only written through the timer handle
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.modify(|_, w| w.moe().clear_bit());
+                }
+
+                /// Sets the levels of the channel output and its complementary output
+                /// while the main output enable is cleared
+                ///
+                /// This also makes the timer drive the idle levels instead of
+                /// releasing the outputs, so gate drivers stay in a safe state after a
+                /// break event. Channels without a complementary output ignore
+                /// `complementary`. Panics if the channel does not exist on the timer.
+                pub fn set_idle_state(
+                    &mut self,
+                    channel: Channel,
+                    output: IdleState,
+                    complementary: IdleState,
+                ) {
+                    match channel {
+                        $(Channel::$C)|+ => {}
+                        #[allow(unreachable_patterns)]
+                        _ => panic!("channel not available on this timer"),
+                    }
+                    // OISxN is reserved on channels without a complementary output
+                    let mask = match channel {
+                        $(Channel::$CN)|+ => 0b11,
+                        #[allow(unreachable_patterns)]
+                        _ => 0b01,
+                    };
+
+                    //NOTE(unsafe) CR2 and BDTR are only written through the timer handle
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let shift = CR2_OIS_SHIFT + 2 * channel as u32;
+                    let bits = ((output == IdleState::High) as u32
+                        | (((complementary == IdleState::High) as u32) << 1))
+                        & mask;
+                    tim.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(mask << shift)) | (bits << shift))
+                    });
+                    tim.bdtr.modify(|_, w| w.ossi().set_bit());
+                }
+
+                /// Returns true if the main output enable is set
+                pub fn outputs_enabled(&self) -> bool {
+                    //NOTE(unsafe) atomic read with no side effects
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.read().moe().bit_is_set()
+                }
+
+                /// Disables the break input
+                pub fn disable_break(&mut self) {
+                    //NOTE(unsafe) BDTR is only written through the timer handle
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.modify(|_, w| w.bke().clear_bit());
+                }
+
+                /// Returns true if a break event occurred since the flag was last cleared
+                pub fn is_break_pending(&self) -> bool {
+                    //NOTE(unsafe) atomic read with no side effects
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.sr.read().bif().bit_is_set()
+                }
+
+                /// Clears the break flag
+                ///
+                /// The outputs stay off until MOE is set again, by software or
+                /// automatically at the next update event if automatic output enable
+                /// is on.
+                pub fn clear_break(&mut self) {
+                    //NOTE(unsafe) the flags are cleared by writing 0, writing 1 has no effect
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.sr.write(|w| unsafe { w.bits(0xffff & !(1 << 7)) });
+                }
+            }
+
+            impl<CHANNELS> PwmChannels<$TIMX, CHANNELS> {
+                /// Returns true if a break event turned the outputs off since the
+                /// last [clear_fault](Self::clear_fault)
+                pub fn fault(&self) -> bool {
+                    self.timer().is_break_pending()
+                }
+
+                /// Clears the break flag and enables the outputs again
+                ///
+                /// The outputs stay off while the break input is still active.
+                pub fn clear_fault(&mut self) {
+                    let mut pwm = self.timer();
+                    pwm.clear_break();
+                    pwm.enable_outputs();
+                }
+            }
+        )+
+    };
+}
+
 // Timer with four output channels 16 Bit Timer
 macro_rules! pwm_4_channels {
     ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
         $(
-            pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
+            pub fn $timX<P, PINS, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                // enable and reset peripheral to a clean slate state
-                rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+                PwmTimer::$timX(tim, pins, rcc, freq).1
+            }
 
-                if PINS::C1 {
-                    tim.ccmr1_output()
-                        .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1() );
-                }
-                if PINS::C2 {
-                    tim.ccmr1_output()
-                        .modify(|_, w| w.oc2pe().set_bit().oc2m().pwm_mode1() );
-                }
-                if PINS::C3 {
-                    tim.ccmr2_output()
-                        .modify(|_, w| w.oc3pe().set_bit().oc3m().pwm_mode1() );
+            impl<PINS> PwmTimer<$TIMX, PINS> {
+                /// Configures the timer like the constructor function of the same
+                /// name, but keeps the timer and the pins so they can be released
+                pub fn $timX<P, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> (Self, PINS::Channels)
+                where
+                    PINS: Pins<$TIMX, P>,
+                    T: Into<Hertz>,
+                {
+                    // enable and reset peripheral to a clean slate state
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIMX));
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+
+                    if PINS::C1 {
+                        tim.ccmr1_output()
+                            .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1() );
+                    }
+                    if PINS::C2 {
+                        tim.ccmr1_output()
+                            .modify(|_, w| w.oc2pe().set_bit().oc2m().pwm_mode1() );
+                    }
+                    if PINS::C3 {
+                        tim.ccmr2_output()
+                            .modify(|_, w| w.oc3pe().set_bit().oc3m().pwm_mode1() );
+                    }
+                    if PINS::C4 {
+                        tim.ccmr2_output()
+                            .modify(|_, w| w.oc4pe().set_bit().oc4m().pwm_mode1() );
+                    }
+
+                    let tclk = rcc.clocks.timclk().0;
+                    let ticks = tclk / freq.into().0;
+
+                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc) );
+                    let arr = u16(ticks / u32(psc + 1)).unwrap();
+                    tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+                    // enable auto-reload preload
+                    tim.cr1.modify(|_, w| w.arpe().set_bit());
+
+                    // Trigger update event to load the registers
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    brk!($TIMX, tim);
+                    tim.cr1.write(|w|
+                        w.cms()
+                            .bits(0b00)
+                            .dir()
+                            .clear_bit()
+                            .opm()
+                            .clear_bit()
+                            .cen()
+                            .set_bit()
+                    );
+                    //NOTE(unsafe) `PINS::Channels` is a ZST
+                    let channels = unsafe { MaybeUninit::uninit().assume_init() };
+                    (PwmTimer { tim, pins }, channels)
+                }
+
+                /// Stops the timer and returns it together with the pins, e.g. to use
+                /// it as a [Timer](crate::timers::Timer) or for input capture
+                ///
+                /// Takes back the channels returned by the constructor, so no channel
+                /// or [Pwm] handle can drive the timer afterwards. The pins stay in
+                /// their alternate function mode.
+                pub fn release<P>(self, _channels: PINS::Channels) -> ($TIMX, PINS)
+                where
+                    PINS: Pins<$TIMX, P>,
+                {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.ccer.reset();
+                    (self.tim, self.pins)
                 }
-                if PINS::C4 {
-                    tim.ccmr2_output()
-                        .modify(|_, w| w.oc4pe().set_bit().oc4m().pwm_mode1() );
-                }
-
-                // If pclk is prescaled from hclk, the frequency fed into the timers is doubled
-                let tclk = if rcc.clocks.hclk().0 == rcc.clocks.pclk().0 {
-                    rcc.clocks.pclk().0
-                } else {
-                    rcc.clocks.pclk().0 * 2
-                };
-                let ticks = tclk / freq.into().0;
-
-                let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-                tim.psc.write(|w| w.psc().bits(psc) );
-                let arr = u16(ticks / u32(psc + 1)).unwrap();
-                tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
-
-                // enable auto-reload preload
-                tim.cr1.modify(|_, w| w.arpe().set_bit());
-
-                // Trigger update event to load the registers
-                tim.cr1.modify(|_, w| w.urs().set_bit());
-                tim.egr.write(|w| w.ug().set_bit());
-                tim.cr1.modify(|_, w| w.urs().clear_bit());
-
-                brk!($TIMX, tim);
-                tim.cr1.write(|w|
-                    w.cms()
-                        .bits(0b00)
-                        .dir()
-                        .clear_bit()
-                        .opm()
-                        .clear_bit()
-                        .cen()
-                        .set_bit()
-                );
-                //NOTE(unsafe) `PINS::Channels` is a ZST
-                unsafe { MaybeUninit::uninit().assume_init() }
             }
 
+            channel_state!($TIMX, C1, cc1e, cc1p);
+            output_mode!($TIMX, C1);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C1> {
                 type Duty = u16;
 
@@ -220,6 +823,9 @@ macro_rules! pwm_4_channels {
                 }
             }
 
+            channel_state!($TIMX, C2, cc2e, cc2p);
+            output_mode!($TIMX, C2);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C2> {
                 type Duty = u16;
 
@@ -249,6 +855,9 @@ macro_rules! pwm_4_channels {
                 }
             }
 
+            channel_state!($TIMX, C3, cc3e, cc3p);
+            output_mode!($TIMX, C3);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C3> {
                 type Duty = u16;
 
@@ -278,6 +887,9 @@ macro_rules! pwm_4_channels {
                 }
             }
 
+            channel_state!($TIMX, C4, cc4e, cc4p);
+            output_mode!($TIMX, C4);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C4> {
                 type Duty = u16;
 
@@ -314,72 +926,99 @@ macro_rules! pwm_4_channels {
 macro_rules! pwm_4_channels_with_3_complementary_outputs {
     ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
         $(
-            pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
+            pub fn $timX<P, PINS, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                // enable and reset peripheral to a clean slate state
-                rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+                PwmTimer::$timX(tim, pins, rcc, freq).1
+            }
 
-                if PINS::C1N | PINS::C1N | PINS::C1N {
-                    tim.bdtr.modify(|_, w| w.ossr().set_bit());
-                }
-                if PINS::C1 {
-                    tim.ccmr1_output()
-                        .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1() );
-                }
-                if PINS::C2 {
-                    tim.ccmr1_output()
-                        .modify(|_, w| w.oc2pe().set_bit().oc2m().pwm_mode1() );
-                }
-                if PINS::C3 {
-                    tim.ccmr2_output()
-                        .modify(|_, w| w.oc3pe().set_bit().oc3m().pwm_mode1() );
+            impl<PINS> PwmTimer<$TIMX, PINS> {
+                /// Configures the timer like the constructor function of the same
+                /// name, but keeps the timer and the pins so they can be released
+                pub fn $timX<P, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> (Self, PINS::Channels)
+                where
+                    PINS: Pins<$TIMX, P>,
+                    T: Into<Hertz>,
+                {
+                    // enable and reset peripheral to a clean slate state
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIMX));
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+
+                    if PINS::C1N | PINS::C1N | PINS::C1N {
+                        tim.bdtr.modify(|_, w| w.ossr().set_bit());
+                    }
+                    if PINS::C1 {
+                        tim.ccmr1_output()
+                            .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1() );
+                    }
+                    if PINS::C2 {
+                        tim.ccmr1_output()
+                            .modify(|_, w| w.oc2pe().set_bit().oc2m().pwm_mode1() );
+                    }
+                    if PINS::C3 {
+                        tim.ccmr2_output()
+                            .modify(|_, w| w.oc3pe().set_bit().oc3m().pwm_mode1() );
+                    }
+                    if PINS::C4 {
+                        tim.ccmr2_output()
+                            .modify(|_, w| w.oc4pe().set_bit().oc4m().pwm_mode1() );
+                    }
+
+                    let tclk = rcc.clocks.timclk().0;
+                    let ticks = tclk / freq.into().0;
+
+                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc) );
+                    let arr = u16(ticks / u32(psc + 1)).unwrap();
+                    tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+                    // enable auto-reload preload
+                    tim.cr1.modify(|_, w| w.arpe().set_bit());
+
+                    // Trigger update event to load the registers
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    brk!($TIMX, tim);
+                    tim.cr1.write(|w|
+                        w.cms()
+                            .bits(0b00)
+                            .dir()
+                            .clear_bit()
+                            .opm()
+                            .clear_bit()
+                            .cen()
+                            .set_bit()
+                    );
+                    //NOTE(unsafe) `PINS::Channels` is a ZST
+                    let channels = unsafe { MaybeUninit::uninit().assume_init() };
+                    (PwmTimer { tim, pins }, channels)
+                }
+
+                /// Stops the timer and returns it together with the pins, e.g. to use
+                /// it as a [Timer](crate::timers::Timer) or for input capture
+                ///
+                /// Takes back the channels returned by the constructor, so no channel
+                /// or [Pwm] handle can drive the timer afterwards. The pins stay in
+                /// their alternate function mode.
+                pub fn release<P>(self, _channels: PINS::Channels) -> ($TIMX, PINS)
+                where
+                    PINS: Pins<$TIMX, P>,
+                {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.ccer.reset();
+                    (self.tim, self.pins)
                 }
-                if PINS::C4 {
-                    tim.ccmr2_output()
-                        .modify(|_, w| w.oc4pe().set_bit().oc4m().pwm_mode1() );
-                }
-
-                // If pclk is prescaled from hclk, the frequency fed into the timers is doubled
-                let tclk = if rcc.clocks.hclk().0 == rcc.clocks.pclk().0 {
-                    rcc.clocks.pclk().0
-                } else {
-                    rcc.clocks.pclk().0 * 2
-                };
-                let ticks = tclk / freq.into().0;
-
-                let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-                tim.psc.write(|w| w.psc().bits(psc) );
-                let arr = u16(ticks / u32(psc + 1)).unwrap();
-                tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
-
-                // enable auto-reload preload
-                tim.cr1.modify(|_, w| w.arpe().set_bit());
-
-                // Trigger update event to load the registers
-                tim.cr1.modify(|_, w| w.urs().set_bit());
-                tim.egr.write(|w| w.ug().set_bit());
-                tim.cr1.modify(|_, w| w.urs().clear_bit());
-
-                brk!($TIMX, tim);
-                tim.cr1.write(|w|
-                    w.cms()
-                        .bits(0b00)
-                        .dir()
-                        .clear_bit()
-                        .opm()
-                        .clear_bit()
-                        .cen()
-                        .set_bit()
-                );
-                //NOTE(unsafe) `PINS::Channels` is a ZST
-                unsafe { MaybeUninit::uninit().assume_init() }
             }
 
+            channel_state!($TIMX, C1, cc1e, cc1p);
+            output_mode!($TIMX, C1);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C1> {
                 type Duty = u16;
 
@@ -409,6 +1048,8 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
                 }
             }
 
+            channel_state!($TIMX, C1N, cc1ne, cc1np);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C1N> {
                 type Duty = u16;
 
@@ -438,6 +1079,9 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
                 }
             }
 
+            channel_state!($TIMX, C2, cc2e, cc2p);
+            output_mode!($TIMX, C2);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C2> {
                 type Duty = u16;
 
@@ -467,6 +1111,8 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
                 }
             }
 
+            channel_state!($TIMX, C2N, cc2ne, cc2np);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C2N> {
                 type Duty = u16;
 
@@ -496,6 +1142,9 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
                 }
             }
 
+            channel_state!($TIMX, C3, cc3e, cc3p);
+            output_mode!($TIMX, C3);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C3> {
                 type Duty = u16;
 
@@ -525,6 +1174,8 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
                 }
             }
 
+            channel_state!($TIMX, C3N, cc3ne, cc3np);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C3N> {
                 type Duty = u16;
 
@@ -554,6 +1205,9 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
                 }
             }
 
+            channel_state!($TIMX, C4, cc4e, cc4p);
+            output_mode!($TIMX, C4);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C4> {
                 type Duty = u16;
 
@@ -586,7 +1240,170 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
     };
 }
 
-// General purpose timer with two output channels
+// Timer with four output channels 32 Bit Timer (TIM2)
+//
+// The full 32 bit auto-reload and compare registers are used, so the duty is
+// a `u32` and low frequencies keep the resolution of the timer clock.
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+macro_rules! pwm_4_channels_32bit {
+    ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
+        $(
+            pub fn $timX<P, PINS, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
+            where
+                PINS: Pins<$TIMX, P>,
+                T: Into<Hertz>,
+            {
+                PwmTimer::$timX(tim, pins, rcc, freq).1
+            }
+
+            impl<PINS> PwmTimer<$TIMX, PINS> {
+                /// Configures the timer like the constructor function of the same
+                /// name, but keeps the timer and the pins so they can be released
+                #[allow(unused_unsafe)]
+                pub fn $timX<P, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> (Self, PINS::Channels)
+                where
+                    PINS: Pins<$TIMX, P>,
+                    T: Into<Hertz>,
+                {
+                    // enable and reset peripheral to a clean slate state
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIMX));
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+
+                    if PINS::C1 {
+                        tim.ccmr1_output()
+                            .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1() );
+                    }
+                    if PINS::C2 {
+                        tim.ccmr1_output()
+                            .modify(|_, w| w.oc2pe().set_bit().oc2m().pwm_mode1() );
+                    }
+                    if PINS::C3 {
+                        tim.ccmr2_output()
+                            .modify(|_, w| w.oc3pe().set_bit().oc3m().pwm_mode1() );
+                    }
+                    if PINS::C4 {
+                        tim.ccmr2_output()
+                            .modify(|_, w| w.oc4pe().set_bit().oc4m().pwm_mode1() );
+                    }
+
+                    // Every frequency fits into the counter without prescaler
+                    let tclk = rcc.clocks.timclk().0;
+                    let ticks = tclk / freq.into().0;
+
+                    tim.psc.write(|w| w.psc().bits(0));
+                    tim.arr.write(|w| unsafe { w.bits(ticks) });
+
+                    // enable auto-reload preload
+                    tim.cr1.modify(|_, w| w.arpe().set_bit());
+
+                    // Trigger update event to load the registers
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    tim.cr1.write(|w|
+                        w.cms()
+                            .bits(0b00)
+                            .dir()
+                            .clear_bit()
+                            .opm()
+                            .clear_bit()
+                            .cen()
+                            .set_bit()
+                    );
+                    //NOTE(unsafe) `PINS::Channels` is a ZST
+                    let channels = unsafe { MaybeUninit::uninit().assume_init() };
+                    (PwmTimer { tim, pins }, channels)
+                }
+
+                /// Stops the timer and returns it together with the pins, e.g. to use
+                /// it as a [Timer](crate::timers::Timer) or for input capture
+                ///
+                /// Takes back the channels returned by the constructor, so no channel
+                /// or [Pwm] handle can drive the timer afterwards. The pins stay in
+                /// their alternate function mode.
+                pub fn release<P>(self, _channels: PINS::Channels) -> ($TIMX, PINS)
+                where
+                    PINS: Pins<$TIMX, P>,
+                {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.ccer.reset();
+                    (self.tim, self.pins)
+                }
+            }
+
+            pwm_channel_32bit!($TIMX, C1, ccr1, cc1e, cc1p);
+            pwm_channel_32bit!($TIMX, C2, ccr2, cc2e, cc2p);
+            pwm_channel_32bit!($TIMX, C3, ccr3, cc3e, cc3p);
+            pwm_channel_32bit!($TIMX, C4, ccr4, cc4e, cc4p);
+        )+
+    };
+}
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+macro_rules! pwm_channel_32bit {
+    ($TIMX:ident, $C:ident, $ccrx:ident, $ccxe:ident, $ccxp:ident) => {
+        channel_state!($TIMX, $C, $ccxe, $ccxp);
+        output_mode!($TIMX, $C);
+
+        impl hal::PwmPin for PwmChannels<$TIMX, $C> {
+            type Duty = u32;
+
+            //NOTE(unsafe) atomic write with no side effects
+            fn disable(&mut self) {
+                unsafe { (*($TIMX::ptr())).ccer.modify(|_, w| w.$ccxe().clear_bit()) };
+            }
+
+            //NOTE(unsafe) atomic write with no side effects
+            fn enable(&mut self) {
+                unsafe { (*($TIMX::ptr())).ccer.modify(|_, w| w.$ccxe().set_bit()) };
+            }
+
+            //NOTE(unsafe) atomic read with no side effects
+            fn get_duty(&self) -> u32 {
+                unsafe { (*$TIMX::ptr()).$ccrx().read().bits() }
+            }
+
+            //NOTE(unsafe) atomic read with no side effects
+            fn get_max_duty(&self) -> u32 {
+                unsafe { (*$TIMX::ptr()).arr.read().bits() }
+            }
+
+            //NOTE(unsafe) atomic write with no side effects
+            fn set_duty(&mut self, duty: u32) {
+                unsafe { (*$TIMX::ptr()).$ccrx().write(|w| w.bits(duty)) }
+            }
+        }
+    };
+}
+
+// General purpose timer with two output channels, channel 1 with complementary output (TIM15)
 #[cfg(any(
     feature = "stm32f030x8",
     feature = "stm32f030xc",
@@ -602,55 +1419,82 @@ macro_rules! pwm_4_channels_with_3_complementary_outputs {
 macro_rules! pwm_2_channels {
     ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
         $(
-            pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
+            pub fn $timX<P, PINS, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                // enable and reset peripheral to a clean slate state
-                rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+                PwmTimer::$timX(tim, pins, rcc, freq).1
+            }
 
-                if PINS::C1 {
-                    tim.ccmr1_output().modify(|_, w| w.oc1pe().set_bit().oc1m().bits(6));
-                }
-                if PINS::C2 {
-                    tim.ccmr1_output().modify(|_, w| w.oc2pe().set_bit().oc2m().bits(6));
+            impl<PINS> PwmTimer<$TIMX, PINS> {
+                /// Configures the timer like the constructor function of the same
+                /// name, but keeps the timer and the pins so they can be released
+                pub fn $timX<P, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> (Self, PINS::Channels)
+                where
+                    PINS: Pins<$TIMX, P>,
+                    T: Into<Hertz>,
+                {
+                    // enable and reset peripheral to a clean slate state
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIMX));
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+
+                    if PINS::C1 || PINS::C1N {
+                        tim.ccmr1_output().modify(|_, w| w.oc1pe().set_bit().oc1m().bits(6));
+                    }
+                    if PINS::C2 {
+                        tim.ccmr1_output().modify(|_, w| w.oc2pe().set_bit().oc2m().bits(6));
+                    }
+
+                    let tclk = rcc.clocks.timclk().0;
+                    let ticks = tclk / freq.into().0;
+
+                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc) );
+                    let arr = u16(ticks / u32(psc + 1)).unwrap();
+                    tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+                    // enable auto-reload preload
+                    tim.cr1.modify(|_, w| w.arpe().set_bit());
+
+                    // Trigger update event to load the registers
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    brk!($TIMX, tim);
+                    tim.cr1.write(|w|
+                        w.opm()
+                            .clear_bit()
+                            .cen()
+                            .set_bit()
+                    );
+                    //NOTE(unsafe) `PINS::Channels` is a ZST
+                    let channels = unsafe { MaybeUninit::uninit().assume_init() };
+                    (PwmTimer { tim, pins }, channels)
+                }
+
+                /// Stops the timer and returns it together with the pins, e.g. to use
+                /// it as a [Timer](crate::timers::Timer) or for input capture
+                ///
+                /// Takes back the channels returned by the constructor, so no channel
+                /// or [Pwm] handle can drive the timer afterwards. The pins stay in
+                /// their alternate function mode.
+                pub fn release<P>(self, _channels: PINS::Channels) -> ($TIMX, PINS)
+                where
+                    PINS: Pins<$TIMX, P>,
+                {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.ccer.reset();
+                    (self.tim, self.pins)
                 }
-
-                // If pclk is prescaled from hclk, the frequency fed into the timers is doubled
-                let tclk = if rcc.clocks.hclk().0 == rcc.clocks.pclk().0 {
-                    rcc.clocks.pclk().0
-                } else {
-                    rcc.clocks.pclk().0 * 2
-                };
-                let ticks = tclk / freq.into().0;
-
-                let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-                tim.psc.write(|w| w.psc().bits(psc) );
-                let arr = u16(ticks / u32(psc + 1)).unwrap();
-                tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
-
-                // enable auto-reload preload
-                tim.cr1.modify(|_, w| w.arpe().set_bit());
-
-                // Trigger update event to load the registers
-                tim.cr1.modify(|_, w| w.urs().set_bit());
-                tim.egr.write(|w| w.ug().set_bit());
-                tim.cr1.modify(|_, w| w.urs().clear_bit());
-
-                brk!($TIMX, tim);
-                tim.cr1.write(|w|
-                    w.opm()
-                        .clear_bit()
-                        .cen()
-                        .set_bit()
-                );
-                //NOTE(unsafe) `PINS::Channels` is a ZST
-                unsafe { MaybeUninit::uninit().assume_init() }
             }
 
+            channel_state!($TIMX, C1, cc1e, cc1p);
+            output_mode!($TIMX, C1);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C1> {
                 type Duty = u16;
 
@@ -680,6 +1524,40 @@ macro_rules! pwm_2_channels {
                 }
             }
 
+            channel_state!($TIMX, C1N, cc1ne, cc1np);
+
+            impl hal::PwmPin for PwmChannels<$TIMX, C1N> {
+                type Duty = u16;
+
+                //NOTE(unsafe) atomic write with no side effects
+                fn disable(&mut self) {
+                    unsafe { (*($TIMX::ptr())).ccer.modify(|_, w| w.cc1ne().clear_bit()) };
+                }
+
+                //NOTE(unsafe) atomic write with no side effects
+                fn enable(&mut self) {
+                    unsafe { (*($TIMX::ptr())).ccer.modify(|_, w| w.cc1ne().set_bit()) };
+                }
+
+                //NOTE(unsafe) atomic read with no side effects
+                fn get_duty(&self) -> u16 {
+                    unsafe { (*$TIMX::ptr()).ccr1().read().ccr().bits() as u16 }
+                }
+
+                //NOTE(unsafe) atomic read with no side effects
+                fn get_max_duty(&self) -> u16 {
+                    unsafe { (*$TIMX::ptr()).arr.read().arr().bits() as u16 }
+                }
+
+                //NOTE(unsafe) atomic write with no side effects
+                fn set_duty(&mut self, duty: u16) {
+                    unsafe { (*$TIMX::ptr()).ccr1().write(|w| w.ccr().bits(duty.into())) }
+                }
+            }
+
+            channel_state!($TIMX, C2, cc2e, cc2p);
+            output_mode!($TIMX, C2);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C2> {
                 type Duty = u16;
 
@@ -716,50 +1594,77 @@ macro_rules! pwm_2_channels {
 macro_rules! pwm_1_channel {
     ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
         $(
-            pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
+            pub fn $timX<P, PINS, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                // enable and reset peripheral to a clean slate state
-                rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+                PwmTimer::$timX(tim, pins, rcc, freq).1
+            }
 
-                if PINS::C1 {
-                    tim.ccmr1_output().modify(|_, w| w.oc1pe().set_bit().oc1m().bits(6));
+            impl<PINS> PwmTimer<$TIMX, PINS> {
+                /// Configures the timer like the constructor function of the same
+                /// name, but keeps the timer and the pins so they can be released
+                pub fn $timX<P, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> (Self, PINS::Channels)
+                where
+                    PINS: Pins<$TIMX, P>,
+                    T: Into<Hertz>,
+                {
+                    // enable and reset peripheral to a clean slate state
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIMX));
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+
+                    if PINS::C1 {
+                        tim.ccmr1_output().modify(|_, w| w.oc1pe().set_bit().oc1m().bits(6));
+                    }
+
+                    let tclk = rcc.clocks.timclk().0;
+                    let ticks = tclk / freq.into().0;
+
+                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc) );
+                    let arr = u16(ticks / u32(psc + 1)).unwrap();
+                    tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+                    // enable auto-reload preload
+                    tim.cr1.modify(|_, w| w.arpe().set_bit());
+
+                    // Trigger update event to load the registers
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    brk!($TIMX, tim);
+                    tim.cr1.write(|w|
+                        w.cen()
+                            .set_bit()
+                    );
+                    //NOTE(unsafe) `PINS::Channels` is a ZST
+                    let channels = unsafe { MaybeUninit::uninit().assume_init() };
+                    (PwmTimer { tim, pins }, channels)
+                }
+
+                /// Stops the timer and returns it together with the pins, e.g. to use
+                /// it as a [Timer](crate::timers::Timer) or for input capture
+                ///
+                /// Takes back the channels returned by the constructor, so no channel
+                /// or [Pwm] handle can drive the timer afterwards. The pins stay in
+                /// their alternate function mode.
+                pub fn release<P>(self, _channels: PINS::Channels) -> ($TIMX, PINS)
+                where
+                    PINS: Pins<$TIMX, P>,
+                {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.ccer.reset();
+                    (self.tim, self.pins)
                 }
-
-                // If pclk is prescaled from hclk, the frequency fed into the timers is doubled
-                let tclk = if rcc.clocks.hclk().0 == rcc.clocks.pclk().0 {
-                    rcc.clocks.pclk().0
-                } else {
-                    rcc.clocks.pclk().0 * 2
-                };
-                let ticks = tclk / freq.into().0;
-
-                let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-                tim.psc.write(|w| w.psc().bits(psc) );
-                let arr = u16(ticks / u32(psc + 1)).unwrap();
-                tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
-
-                // enable auto-reload preload
-                tim.cr1.modify(|_, w| w.arpe().set_bit());
-
-                // Trigger update event to load the registers
-                tim.cr1.modify(|_, w| w.urs().set_bit());
-                tim.egr.write(|w| w.ug().set_bit());
-                tim.cr1.modify(|_, w| w.urs().clear_bit());
-
-                brk!($TIMX, tim);
-                tim.cr1.write(|w|
-                    w.cen()
-                        .set_bit()
-                );
-                //NOTE(unsafe) `PINS::Channels` is a ZST
-                unsafe { MaybeUninit::uninit().assume_init() }
             }
 
+            channel_state!($TIMX, C1, cc1e, cc1p);
+            output_mode!($TIMX, C1);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C1> {
                 type Duty = u16;
 
@@ -796,53 +1701,80 @@ macro_rules! pwm_1_channel {
 macro_rules! pwm_1_channel_with_complementary_outputs {
     ($($TIMX:ident: ($timX:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
         $(
-            pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
+            pub fn $timX<P, PINS, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                // enable and reset peripheral to a clean slate state
-                rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
-                rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+                PwmTimer::$timX(tim, pins, rcc, freq).1
+            }
 
-                if PINS::C1 || PINS::C1N {
-                    tim.ccmr1_output().modify(|_, w| w.oc1pe().set_bit().oc1m().bits(6));
+            impl<PINS> PwmTimer<$TIMX, PINS> {
+                /// Configures the timer like the constructor function of the same
+                /// name, but keeps the timer and the pins so they can be released
+                pub fn $timX<P, T>(tim: $TIMX, pins: PINS, rcc: &mut Rcc, freq: T) -> (Self, PINS::Channels)
+                where
+                    PINS: Pins<$TIMX, P>,
+                    T: Into<Hertz>,
+                {
+                    // enable and reset peripheral to a clean slate state
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIMX));
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
+                    rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+
+                    if PINS::C1 || PINS::C1N {
+                        tim.ccmr1_output().modify(|_, w| w.oc1pe().set_bit().oc1m().bits(6));
+                    }
+
+                    let tclk = rcc.clocks.timclk().0;
+                    let ticks = tclk / freq.into().0;
+
+                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc) );
+                    let arr = u16(ticks / u32(psc + 1)).unwrap();
+                    tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+                    // enable auto-reload preload
+                    tim.cr1.modify(|_, w| w.arpe().set_bit());
+
+                    // Trigger update event to load the registers
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    brk!($TIMX, tim);
+                    tim.cr1.write(|w|
+                        w.opm()
+                            .clear_bit()
+                            .cen()
+                            .set_bit()
+                    );
+
+                    //NOTE(unsafe) `PINS::Channels` is a ZST
+                    let channels = unsafe { MaybeUninit::uninit().assume_init() };
+                    (PwmTimer { tim, pins }, channels)
+                }
+
+                /// Stops the timer and returns it together with the pins, e.g. to use
+                /// it as a [Timer](crate::timers::Timer) or for input capture
+                ///
+                /// Takes back the channels returned by the constructor, so no channel
+                /// or [Pwm] handle can drive the timer afterwards. The pins stay in
+                /// their alternate function mode.
+                pub fn release<P>(self, _channels: PINS::Channels) -> ($TIMX, PINS)
+                where
+                    PINS: Pins<$TIMX, P>,
+                {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.ccer.reset();
+                    (self.tim, self.pins)
                 }
-
-                // If pclk is prescaled from hclk, the frequency fed into the timers is doubled
-                let tclk = if rcc.clocks.hclk().0 == rcc.clocks.pclk().0 {
-                    rcc.clocks.pclk().0
-                } else {
-                    rcc.clocks.pclk().0 * 2
-                };
-                let ticks = tclk / freq.into().0;
-
-                let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-                tim.psc.write(|w| w.psc().bits(psc) );
-                let arr = u16(ticks / u32(psc + 1)).unwrap();
-                tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
-
-                // enable auto-reload preload
-                tim.cr1.modify(|_, w| w.arpe().set_bit());
-
-                // Trigger update event to load the registers
-                tim.cr1.modify(|_, w| w.urs().set_bit());
-                tim.egr.write(|w| w.ug().set_bit());
-                tim.cr1.modify(|_, w| w.urs().clear_bit());
-
-                brk!($TIMX, tim);
-                tim.cr1.write(|w|
-                    w.opm()
-                        .clear_bit()
-                        .cen()
-                        .set_bit()
-                );
-
-                //NOTE(unsafe) `PINS::Channels` is a ZST
-                unsafe { MaybeUninit::uninit().assume_init() }
             }
 
+            channel_state!($TIMX, C1, cc1e, cc1p);
+            output_mode!($TIMX, C1);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C1> {
                 type Duty = u16;
 
@@ -872,6 +1804,8 @@ macro_rules! pwm_1_channel_with_complementary_outputs {
                 }
             }
 
+            channel_state!($TIMX, C1N, cc1ne, cc1np);
+
             impl hal::PwmPin for PwmChannels<$TIMX, C1N> {
                 type Duty = u16;
 
@@ -911,6 +1845,21 @@ pwm_4_channels!(TIM3: (tim3, tim3en, tim3rst, apb1enr, apb1rstr),);
 pwm_4_channels_with_3_complementary_outputs!(TIM1: (tim1, tim1en, tim1rst, apb2enr, apb2rstr),);
 pwm_1_channel!(TIM14: (tim14, tim14en, tim14rst, apb1enr, apb1rstr),);
 
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+pwm_4_channels_32bit!(TIM2: (tim2, tim2en, tim2rst, apb1enr, apb1rstr),);
+
 pwm_1_channel_with_complementary_outputs!(
     TIM16: (tim16, tim16en, tim16rst, apb2enr, apb2rstr),
     TIM17: (tim17, tim17en, tim17rst, apb2enr, apb2rstr),
@@ -931,3 +1880,184 @@ pwm_1_channel_with_complementary_outputs!(
 pwm_2_channels! {
     TIM15: (tim15, tim15en, tim15rst, apb2enr, apb2rstr),
 }
+
+pwm_timer! {
+    TIM1: [C1 => ccr1, C2 => ccr2, C3 => ccr3, C4 => ccr4],
+    TIM3: [C1 => ccr1, C2 => ccr2, C3 => ccr3, C4 => ccr4],
+    TIM14: [C1 => ccr1],
+    TIM16: [C1 => ccr1],
+    TIM17: [C1 => ccr1],
+}
+
+pwm_break! {
+    TIM1: [C1, C2, C3, C4], [C1, C2, C3],
+    TIM16: [C1], [C1],
+    TIM17: [C1], [C1],
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+pwm_timer! {
+    TIM15: [C1 => ccr1, C2 => ccr2],
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+pwm_break! {
+    TIM15: [C1, C2], [C1],
+}
+
+/// Configures channel 1 of `tim` to output a pulse of `width` after `delay`,
+/// e.g. to trigger a camera or drive an ultrasonic transducer
+///
+/// Returns the [OnePulse](crate::timers::OnePulse) timer, every
+/// [trigger](crate::timers::OnePulse::trigger) outputs one pulse. The
+/// resolution is chosen to fit delay and width, see
+/// [set_timing](crate::timers::OnePulse::set_timing).
+pub fn one_pulse<TIM, PIN, D, W>(
+    tim: TIM,
+    pin: PIN,
+    rcc: &mut Rcc,
+    delay: D,
+    width: W,
+) -> OnePulse<TIM>
+where
+    TIM: OnePulseTimer,
+    PIN: PinC1<TIM>,
+    D: Into<MicroSecond>,
+    W: Into<MicroSecond>,
+{
+    tim.into_one_pulse(pin, rcc, delay.into(), width.into())
+}
+
+/// End point of a [PwmSweep]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweepPoint {
+    /// PWM frequency
+    pub frequency: Hertz,
+    /// Duty cycle in percent
+    pub duty_percent: u8,
+}
+
+/// Linear ramp of PWM frequency and duty, e.g. for buzzer chirps or the soft
+/// start of a pump
+///
+/// The ramp is advanced from the interrupt of a tick timer. The tick timer has
+/// to listen for its timeout event, [on_interrupt](PwmSweep::on_interrupt)
+/// clears it.
+pub struct PwmSweep<TIM, CH, TICK> {
+    channel: PwmChannels<TIM, CH>,
+    tick: TICK,
+    tick_rate: u32,
+    timer_clock: u32,
+    from: SweepPoint,
+    to: SweepPoint,
+    steps: u32,
+    step: u32,
+}
+
+impl<TIM, CH, TICK> PwmSweep<TIM, CH, TICK>
+where
+    PwmChannels<TIM, CH>: hal::PwmPin<Duty = u16>,
+    for<'a> Pwm<'a, TIM>: Period,
+    TICK: CountDown<Time = Hertz> + Periodic,
+{
+    /// Creates a sweep on `channel`, advanced `tick_rate` times per second by
+    /// `tick`
+    pub fn new<T>(channel: PwmChannels<TIM, CH>, tick: TICK, tick_rate: T, rcc: &Rcc) -> Self
+    where
+        T: Into<Hertz>,
+    {
+        let point = SweepPoint {
+            frequency: Hertz(0),
+            duty_percent: 0,
+        };
+        PwmSweep {
+            channel,
+            tick,
+            tick_rate: tick_rate.into().0,
+            timer_clock: rcc.clocks.timclk().0,
+            from: point,
+            to: point,
+            steps: 0,
+            step: 0,
+        }
+    }
+
+    /// Starts ramping from `from` to `to` within `duration`
+    ///
+    /// The output jumps to `from` immediately. A running sweep is replaced.
+    /// Frequencies are clamped to the range from 1 Hz to the timer clock.
+    pub fn start<T>(&mut self, from: SweepPoint, to: SweepPoint, duration: T)
+    where
+        T: Into<MicroSecond>,
+    {
+        let steps = u64::from(duration.into().0) * u64::from(self.tick_rate) / 1_000_000;
+        self.from = from;
+        self.to = to;
+        self.steps = u32(steps).unwrap_or(u32::MAX).max(1);
+        self.step = 0;
+        self.apply(from.frequency.0, u32::from(from.duty_percent));
+        self.tick.start(Hertz(self.tick_rate));
+    }
+
+    /// Returns true while the ramp has not reached its end point
+    pub fn is_running(&self) -> bool {
+        self.step < self.steps
+    }
+
+    /// Advances the ramp, call this from the interrupt handler of the tick timer
+    pub fn on_interrupt(&mut self) {
+        if self.tick.wait().is_err() || !self.is_running() {
+            return;
+        }
+        self.step += 1;
+
+        let frequency = self.interpolate(self.from.frequency.0, self.to.frequency.0);
+        let duty = self.interpolate(
+            u32::from(self.from.duty_percent),
+            u32::from(self.to.duty_percent),
+        );
+        self.apply(frequency, duty);
+    }
+
+    /// Releases the PWM channel and the tick timer
+    pub fn release(self) -> (PwmChannels<TIM, CH>, TICK) {
+        (self.channel, self.tick)
+    }
+
+    fn interpolate(&self, from: u32, to: u32) -> u32 {
+        let delta = i64::from(to) - i64::from(from);
+        (i64::from(from) + delta * i64::from(self.step) / i64::from(self.steps)) as u32
+    }
+
+    fn apply(&mut self, frequency: u32, duty_percent: u32) {
+        use hal::PwmPin;
+
+        let cycles = self.timer_clock / frequency.max(1);
+        self.channel.timer().set_period(cycles.max(1));
+        let max_duty = u32::from(self.channel.get_max_duty());
+        self.channel
+            .set_duty((max_duty * duty_percent.min(100) / 100) as u16);
+    }
+}
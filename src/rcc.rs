@@ -106,7 +106,8 @@ mod inner {
         }
     }
 
-    pub(super) fn enable_clock(rcc: &mut RCC, c_src: &SysClkSource) {
+    /// Enables the clock source, returns false if it did not become ready
+    pub(super) fn enable_clock(rcc: &mut RCC, c_src: &SysClkSource) -> bool {
         // Enable the requested clock
         match c_src {
             SysClkSource::HSE(_, bypassed) => {
@@ -120,11 +121,18 @@ mod inner {
                             .modify(|_, w| w.csson().on().hseon().on().hsebyp().bypassed());
                     }
                 }
-                while !rcc.cr.read().hserdy().bit_is_set() {}
+
+                let ready = super::wait_ready(|| rcc.cr.read().hserdy().bit_is_set());
+                if !ready {
+                    // Turn the broken oscillator off again
+                    rcc.cr.modify(|_, w| w.csson().off().hseon().off());
+                }
+                ready
             }
             SysClkSource::HSI => {
                 rcc.cr.write(|w| w.hsion().set_bit());
                 while rcc.cr.read().hsirdy().bit_is_clear() {}
+                true
             }
         }
     }
@@ -243,7 +251,8 @@ mod inner {
         }
     }
 
-    pub(super) fn enable_clock(rcc: &mut RCC, c_src: &SysClkSource) {
+    /// Enables the clock source, returns false if it did not become ready
+    pub(super) fn enable_clock(rcc: &mut RCC, c_src: &SysClkSource) -> bool {
         // Enable the requested clock
         match c_src {
             SysClkSource::HSE(_, bypassed) => {
@@ -258,7 +267,12 @@ mod inner {
                     }
                 }
 
-                while !rcc.cr.read().hserdy().bit_is_set() {}
+                let ready = super::wait_ready(|| rcc.cr.read().hserdy().bit_is_set());
+                if !ready {
+                    // Turn the broken oscillator off again
+                    rcc.cr.modify(|_, w| w.csson().off().hseon().off());
+                }
+                ready
             }
             #[cfg(any(
                 feature = "stm32f042",
@@ -272,10 +286,12 @@ mod inner {
             SysClkSource::HSI48 => {
                 rcc.cr2.modify(|_, w| w.hsi48on().set_bit());
                 while rcc.cr2.read().hsi48rdy().bit_is_clear() {}
+                true
             }
             SysClkSource::HSI => {
                 rcc.cr.write(|w| w.hsion().set_bit());
                 while rcc.cr.read().hsirdy().bit_is_clear() {}
+                true
             }
         }
     }
@@ -334,6 +350,14 @@ mod inner {
 
 use self::inner::SysClkSource;
 
+/// Number of status polls before the HSE is considered broken
+const HSE_STARTUP_TIMEOUT: u32 = 0x5000;
+
+/// Polls `ready` until it returns true, at most `HSE_STARTUP_TIMEOUT` times
+fn wait_ready(ready: impl Fn() -> bool) -> bool {
+    (0..HSE_STARTUP_TIMEOUT).any(|_| ready())
+}
+
 pub struct CFGR {
     hclk: Option<u32>,
     pclk: Option<u32>,
@@ -434,7 +458,41 @@ impl CFGR {
         self
     }
 
-    pub fn freeze(mut self, flash: &mut crate::pac::FLASH) -> Rcc {
+    /// Applies the clock configuration
+    ///
+    /// If the HSE was selected but does not start, e.g. because of a broken crystal, the
+    /// clocks are derived from the HSI instead. [Clocks::hse_fallback] reports this.
+    pub fn freeze(self, flash: &mut crate::pac::FLASH) -> Rcc {
+        match self.freeze_with(flash, true) {
+            Ok(rcc) => rcc,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Applies the clock configuration
+    ///
+    /// Returns an error instead of falling back to the HSI if the HSE does not start.
+    pub fn try_freeze(self, flash: &mut crate::pac::FLASH) -> Result<Rcc, ClockError> {
+        self.freeze_with(flash, false)
+    }
+
+    fn freeze_with(
+        mut self,
+        flash: &mut crate::pac::FLASH,
+        fallback: bool,
+    ) -> Result<Rcc, ClockError> {
+        // Start the HSE first, so a broken crystal is noticed before anything is changed
+        let mut hse_fallback = false;
+        if let SysClkSource::HSE(_, _) = self.clock_src {
+            if !self::inner::enable_clock(&mut self.rcc, &self.clock_src) {
+                if !fallback {
+                    return Err(ClockError::HseTimeout);
+                }
+                self.clock_src = SysClkSource::HSI;
+                hse_fallback = true;
+            }
+        }
+
         // Default to lowest frequency clock on all systems.
         let sysclk = self.sysclk.unwrap_or(self::inner::HSI);
 
@@ -487,7 +545,7 @@ impl CFGR {
             })
             .unwrap_or(0b0111);
 
-        let hclk = r_sysclk / (1 << (hpre_bits - 0b0111));
+        let hclk = r_sysclk / hpre_divider(hpre_bits);
 
         let ppre_bits = self
             .pclk
@@ -501,8 +559,7 @@ impl CFGR {
             })
             .unwrap_or(0b011);
 
-        let ppre: u8 = 1 << (ppre_bits - 0b011);
-        let pclk = hclk / cast::u32(ppre);
+        let (hclk, pclk, timclk) = bus_clocks(r_sysclk, hpre_bits, ppre_bits);
 
         // adjust flash wait states
         unsafe {
@@ -517,7 +574,7 @@ impl CFGR {
             })
         }
 
-        // Enable the requested clock
+        // Enable the requested clock, the HSE is already running at this point
         self::inner::enable_clock(&mut self.rcc, &self.clock_src);
 
         // Only need to set USBSW if MCU has USB HW
@@ -585,17 +642,75 @@ impl CFGR {
                     .variant(sw_var)
             });
         }
-        Rcc {
+        Ok(Rcc {
             clocks: Clocks {
                 hclk: Hertz(hclk),
                 pclk: Hertz(pclk),
                 sysclk: Hertz(sysclk),
+                timclk: Hertz(timclk),
+                hse_fallback,
             },
             regs: self.rcc,
-        }
+        })
+    }
+}
+
+/// Returns the divider selected by the HPRE bits, /32 is not available
+fn hpre_divider(hpre_bits: u8) -> u32 {
+    match hpre_bits {
+        0b1000 => 2,
+        0b1001 => 4,
+        0b1010 => 8,
+        0b1011 => 16,
+        0b1100 => 64,
+        0b1101 => 128,
+        0b1110 => 256,
+        0b1111 => 512,
+        _ => 1,
+    }
+}
+
+/// Returns the divider selected by the PPRE bits
+fn ppre_divider(ppre_bits: u8) -> u32 {
+    match ppre_bits {
+        0b100 => 2,
+        0b101 => 4,
+        0b110 => 8,
+        0b111 => 16,
+        _ => 1,
     }
 }
 
+/// Derives AHB, APB and timer clock from the system clock and the prescaler bits
+fn bus_clocks(sysclk: u32, hpre_bits: u8, ppre_bits: u8) -> (u32, u32, u32) {
+    let hclk = sysclk / hpre_divider(hpre_bits);
+    let ppre = ppre_divider(ppre_bits);
+    let pclk = hclk / ppre;
+    // If pclk is prescaled from hclk, the frequency fed into the timers is doubled
+    let timclk = if ppre == 1 { pclk } else { pclk * 2 };
+    (hclk, pclk, timclk)
+}
+
+/// Error returned by [CFGR::try_freeze]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockError {
+    /// The HSE did not become ready in time
+    HseTimeout,
+}
+
+/// Error returned by the `try_` constructors
+///
+/// The requested rate cannot be derived from the frozen clocks
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Requested frequency is above what the peripheral clock can produce
+    FrequencyTooHigh,
+    /// Requested frequency is below what the dividers can reach
+    FrequencyTooLow,
+}
+
 /// Frozen clock frequencies
 ///
 /// The existence of this value indicates that the clock configuration can no longer be changed
@@ -604,6 +719,8 @@ pub struct Clocks {
     hclk: Hertz,
     pclk: Hertz,
     sysclk: Hertz,
+    timclk: Hertz,
+    hse_fallback: bool,
 }
 
 impl Clocks {
@@ -617,8 +734,79 @@ impl Clocks {
         self.pclk
     }
 
+    /// Returns the frequency fed into the timers
+    ///
+    /// This is twice the APB frequency if the APB is prescaled from the AHB.
+    pub fn timclk(&self) -> Hertz {
+        self.timclk
+    }
+
     /// Returns the system (core) frequency
     pub fn sysclk(&self) -> Hertz {
         self.sysclk
     }
+
+    /// Returns true if the HSE failed to start and the HSI is used instead
+    pub fn hse_fallback(&self) -> bool {
+        self.hse_fallback
+    }
+
+    /// Returns the kernel clock of the peripheral `P`, e.g.
+    /// `clocks.freq_of::<USART1>()`
+    pub fn freq_of<P: BusClock>(&self) -> Hertz {
+        P::clock(self)
+    }
+}
+
+/// Peripheral with a known kernel clock
+pub trait BusClock {
+    /// Returns the clock the peripheral derives its timing from
+    fn clock(clocks: &Clocks) -> Hertz;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bus_clocks_of_all_prescalers() {
+        let hpre = [
+            (0b0111, 1),
+            (0b1000, 2),
+            (0b1001, 4),
+            (0b1010, 8),
+            (0b1011, 16),
+            (0b1100, 64),
+            (0b1101, 128),
+            (0b1110, 256),
+            (0b1111, 512),
+        ];
+        let ppre = [(0b011, 1), (0b100, 2), (0b101, 4), (0b110, 8), (0b111, 16)];
+        let sysclk = 48_000_000;
+
+        for &(hpre_bits, hdiv) in hpre.iter() {
+            for &(ppre_bits, pdiv) in ppre.iter() {
+                let (hclk, pclk, timclk) = bus_clocks(sysclk, hpre_bits, ppre_bits);
+                assert_eq!(hclk, sysclk / hdiv);
+                assert_eq!(pclk, sysclk / hdiv / pdiv);
+                let expected = if pdiv == 1 { pclk } else { 2 * pclk };
+                assert_eq!(
+                    timclk, expected,
+                    "hpre {:#b} ppre {:#b}",
+                    hpre_bits, ppre_bits
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn timclk_never_exceeds_hclk() {
+        for hpre_bits in 0b0111..=0b1111 {
+            for ppre_bits in 0b011..=0b111 {
+                let (hclk, pclk, timclk) = bus_clocks(48_000_000, hpre_bits, ppre_bits);
+                assert!(pclk <= hclk);
+                assert!(timclk <= hclk);
+            }
+        }
+    }
 }
@@ -1,6 +1,7 @@
 //! API for the integrated USART ports
 //!
-//! This only implements the usual asynchronous bidirectional 8-bit transfers.
+//! This only implements the usual asynchronous bidirectional transfers of up
+//! to 8 data bits, the frame format is set with [Config].
 //!
 //! It's possible to use a read-only/write-only serial implementation with
 //! `usartXrx`/`usartXtx`.
@@ -66,7 +67,15 @@ use core::{
 
 use embedded_hal::prelude::*;
 
-use crate::{gpio::*, rcc::Rcc, time::Bps};
+use cortex_m::interrupt::CriticalSection;
+use cortex_m::peripheral::NVIC;
+
+use crate::{
+    gpio::*,
+    pac::Interrupt,
+    rcc::{BusClock, Clocks, ConfigError, Rcc},
+    time::{Bps, Hertz},
+};
 
 use core::marker::PhantomData;
 
@@ -79,11 +88,153 @@ pub enum Error {
     /// Noise error
     Noise,
     /// RX buffer overrun
+    ///
+    /// Not reported if overrun detection is disabled, see `set_overrun_detection`.
     Overrun,
     /// Parity check error
     Parity,
 }
 
+/// Word length including the parity bit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WordLength {
+    /// 7 bits, only on STM32F04x, STM32F07x and STM32F09x
+    #[cfg(any(
+        feature = "stm32f042",
+        feature = "stm32f048",
+        feature = "stm32f071",
+        feature = "stm32f072",
+        feature = "stm32f078",
+        feature = "stm32f091",
+        feature = "stm32f098",
+    ))]
+    DataBits7,
+    /// 8 bits
+    DataBits8,
+    /// 9 bits
+    DataBits9,
+}
+
+/// Parity bit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Parity {
+    /// No parity bit
+    ParityNone,
+    /// Even parity
+    ParityEven,
+    /// Odd parity
+    ParityOdd,
+}
+
+/// Number of stop bits
+///
+/// The USARTs of the STM32F0 don't support 0.5 stop bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopBits {
+    /// 1 stop bit
+    STOP1,
+    /// 1.5 stop bits
+    STOP1P5,
+    /// 2 stop bits
+    STOP2,
+}
+
+/// Serial port configuration
+///
+/// The word length includes the parity bit, i.e. 8E1 is
+/// `Config::default().wordlength_9().parity_even()` and 7E1 is
+/// `Config::default().parity_even()`. Received parity bits are masked off.
+/// A baud rate converts into a configuration with 8N1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// Baud rate
+    pub baudrate: Bps,
+    /// Word length including the parity bit
+    pub wordlength: WordLength,
+    /// Parity bit
+    pub parity: Parity,
+    /// Number of stop bits
+    pub stopbits: StopBits,
+}
+
+impl Config {
+    /// Sets the baud rate
+    pub fn baudrate(mut self, baudrate: Bps) -> Self {
+        self.baudrate = baudrate;
+        self
+    }
+
+    /// Disables the parity bit
+    pub fn parity_none(mut self) -> Self {
+        self.parity = Parity::ParityNone;
+        self
+    }
+
+    /// Adds an even parity bit
+    pub fn parity_even(mut self) -> Self {
+        self.parity = Parity::ParityEven;
+        self
+    }
+
+    /// Adds an odd parity bit
+    pub fn parity_odd(mut self) -> Self {
+        self.parity = Parity::ParityOdd;
+        self
+    }
+
+    /// Sets a word length of 7 bits
+    #[cfg(any(
+        feature = "stm32f042",
+        feature = "stm32f048",
+        feature = "stm32f071",
+        feature = "stm32f072",
+        feature = "stm32f078",
+        feature = "stm32f091",
+        feature = "stm32f098",
+    ))]
+    pub fn wordlength_7(mut self) -> Self {
+        self.wordlength = WordLength::DataBits7;
+        self
+    }
+
+    /// Sets a word length of 8 bits
+    pub fn wordlength_8(mut self) -> Self {
+        self.wordlength = WordLength::DataBits8;
+        self
+    }
+
+    /// Sets a word length of 9 bits
+    ///
+    /// Without parity the ninth data bit is dropped by `read` and sent as 0.
+    pub fn wordlength_9(mut self) -> Self {
+        self.wordlength = WordLength::DataBits9;
+        self
+    }
+
+    /// Sets the number of stop bits
+    pub fn stopbits(mut self, stopbits: StopBits) -> Self {
+        self.stopbits = stopbits;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            baudrate: Bps(115_200),
+            wordlength: WordLength::DataBits8,
+            parity: Parity::ParityNone,
+            stopbits: StopBits::STOP1,
+        }
+    }
+}
+
+impl From<Bps> for Config {
+    fn from(baudrate: Bps) -> Self {
+        Config::default().baudrate(baudrate)
+    }
+}
+
 /// Interrupt event
 pub enum Event {
     /// New data has been received
@@ -94,20 +245,69 @@ pub enum Event {
     Idle,
 }
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// USART peripheral
+///
+/// This allows writing code generic over all USART peripherals.
+pub trait Instance:
+    sealed::Sealed + BusClock + Deref<Target = crate::pac::usart1::RegisterBlock>
+{
+    /// Enables the clock of the peripheral
+    fn enable_clock(rcc: &mut Rcc);
+
+    /// Disables the clock of the peripheral
+    fn disable_clock(rcc: &mut Rcc);
+}
+
+/// Pin usable as TX of `USART`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as TX of `{USART}`",
+    note = "check that the pin provides this function and is set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait TxPin<USART> {}
+/// Pin usable as RX of `USART`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as RX of `{USART}`",
+    note = "check that the pin provides this function and is set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait RxPin<USART> {}
 
+// Erased pins are vouched for by the `unsafe` constructor of `Unchecked`
+impl<USART, AF> TxPin<USART> for Unchecked<Pin<Alternate<AF>>> {}
+impl<USART, AF> TxPin<USART> for Unchecked<ErasedPin<Alternate<AF>>> {}
+impl<USART, AF> RxPin<USART> for Unchecked<Pin<Alternate<AF>>> {}
+impl<USART, AF> RxPin<USART> for Unchecked<ErasedPin<Alternate<AF>>> {}
+
 macro_rules! usart_pins {
     ($($USART:ident => {
-        tx => [$($tx:ty),+ $(,)*],
-        rx => [$($rx:ty),+ $(,)*],
+        tx => [$($txport:ident::$tx:ident<Alternate<$txaf:ident>>),+ $(,)*],
+        rx => [$($rxport:ident::$rx:ident<Alternate<$rxaf:ident>>),+ $(,)*],
     })+) => {
         $(
             $(
-                impl TxPin<crate::pac::$USART> for $tx {}
+                impl TxPin<crate::pac::$USART> for $txport::$tx<Alternate<$txaf>> {}
+
+                impl<MODE> ValidPin<crate::pac::$USART, signal::Tx> for $txport::$tx<MODE> {
+                    type Configured = $txport::$tx<Alternate<$txaf>>;
+
+                    fn setup(self, cs: &CriticalSection) -> Self::Configured {
+                        self.into_alternate(cs)
+                    }
+                }
             )+
             $(
-                impl RxPin<crate::pac::$USART> for $rx {}
+                impl RxPin<crate::pac::$USART> for $rxport::$rx<Alternate<$rxaf>> {}
+
+                impl<MODE> ValidPin<crate::pac::$USART, signal::Rx> for $rxport::$rx<MODE> {
+                    type Configured = $rxport::$rx<Alternate<$rxaf>>;
+
+                    fn setup(self, cs: &CriticalSection) -> Self::Configured {
+                        self.into_alternate(cs)
+                    }
+                }
             )+
         )+
     }
@@ -283,24 +483,151 @@ pub struct Tx<USART> {
 // NOTE(unsafe) Required to allow protected shared access in handlers
 unsafe impl<USART> Send for Tx<USART> {}
 
+// Frame format bits, written raw as M1 only exists on the STM32F04x, STM32F07x
+// and STM32F09x
+const CR1_M1: u32 = 1 << 28;
+const CR1_M0: u32 = 1 << 12;
+const CR1_PCE: u32 = 1 << 10;
+const CR1_PS: u32 = 1 << 9;
+const CR2_STOP_SHIFT: u32 = 12;
+const CR2_STOP_MASK: u32 = 0b11 << CR2_STOP_SHIFT;
+
+/// Returns the mask of the data bits of a received word, without parity bit
+fn data_mask(cr1: u32) -> u16 {
+    let mut bits = if cr1 & CR1_M1 != 0 {
+        7
+    } else if cr1 & CR1_M0 != 0 {
+        9
+    } else {
+        8
+    };
+    if cr1 & CR1_PCE != 0 {
+        bits -= 1;
+    }
+    (1 << bits) - 1
+}
+
+/// Checks that `baud_rate` can be derived from `pclk` with 16x oversampling
+fn check_baud_rate(pclk: Hertz, baud_rate: Bps) -> core::result::Result<(), ConfigError> {
+    if baud_rate.0 == 0 {
+        return Err(ConfigError::FrequencyTooLow);
+    }
+    match pclk.0 / baud_rate.0 {
+        0..=15 => Err(ConfigError::FrequencyTooHigh),
+        0x1_0000..=u32::MAX => Err(ConfigError::FrequencyTooLow),
+        _ => Ok(()),
+    }
+}
+
 macro_rules! usart {
-    ($($USART:ident: ($usart:ident, $usarttx:ident, $usartrx:ident, $usartXen:ident, $apbenr:ident),)+) => {
+    ($($USART:ident: ($usart:ident, $try_usart:ident, $usart_setup:ident, $usarttx:ident, $usartrx:ident, $usartXen:ident, $apbenr:ident),)+) => {
         $(
             use crate::pac::$USART;
+
+            impl sealed::Sealed for $USART {}
+
+            impl Instance for $USART {
+                fn enable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$usartXen().set_bit());
+                    clock_event!(enabled, stringify!($USART));
+                }
+
+                fn disable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$usartXen().clear_bit());
+                    clock_event!(disabled, stringify!($USART));
+                }
+            }
+
+            // The USART clock selection is left at its reset default, PCLK
+            impl BusClock for $USART {
+                fn clock(clocks: &Clocks) -> Hertz {
+                    clocks.pclk()
+                }
+            }
             impl<TXPIN, RXPIN> Serial<$USART, TXPIN, RXPIN>
             where
                 TXPIN: TxPin<$USART>,
                 RXPIN: RxPin<$USART>,
             {
                 /// Creates a new serial instance
-                pub fn $usart(usart: $USART, pins: (TXPIN, RXPIN), baud_rate: Bps, rcc: &mut Rcc) -> Self
+                ///
+                /// Takes a baud rate for 8N1 or a [Config].
+                pub fn $usart<C>(usart: $USART, pins: (TXPIN, RXPIN), config: C, rcc: &mut Rcc) -> Self
+                where
+                    C: Into<Config>,
                 {
                     let mut serial = Serial { usart, pins };
-                    serial.configure(baud_rate, rcc);
+                    serial.configure(config.into(), rcc);
                     // Enable transmission and receiving
                     serial.usart.cr1.modify(|_, w| w.te().set_bit().re().set_bit().ue().set_bit());
                     serial
                 }
+
+                /// Creates a new serial instance
+                ///
+                /// Returns an error instead of misconfiguring the port if the baud rate cannot be reached
+                pub fn $try_usart<C>(
+                    usart: $USART,
+                    pins: (TXPIN, RXPIN),
+                    config: C,
+                    rcc: &mut Rcc,
+                ) -> core::result::Result<Self, ConfigError>
+                where
+                    C: Into<Config>,
+                {
+                    let config = config.into();
+                    check_baud_rate(rcc.clocks.pclk(), config.baudrate)?;
+                    Ok(Self::$usart(usart, pins, config, rcc))
+                }
+
+                /// Creates a new serial instance from pins in any mode
+                ///
+                /// The pins are switched to the alternate function of their signal.
+                pub fn $usart_setup<TX, RX, C>(
+                    usart: $USART,
+                    pins: (TX, RX),
+                    config: C,
+                    rcc: &mut Rcc,
+                    cs: &CriticalSection,
+                ) -> Self
+                where
+                    TX: ValidPin<$USART, signal::Tx, Configured = TXPIN>,
+                    RX: ValidPin<$USART, signal::Rx, Configured = RXPIN>,
+                    C: Into<Config>,
+                {
+                    let pins = (pins.0.setup(cs), pins.1.setup(cs));
+                    Self::$usart(usart, pins, config, rcc)
+                }
+            }
+
+            impl Tx<$USART> {
+                /// Creates the transmitter without configuring the USART, e.g. for `static` items
+                ///
+                /// # Safety
+                ///
+                /// The USART must already be configured for transmission and the
+                /// transmitter must not be in use anywhere else.
+                pub const unsafe fn new_unchecked() -> Self {
+                    Tx {
+                        usart: $USART::ptr(),
+                        _instance: PhantomData,
+                    }
+                }
+            }
+
+            impl Rx<$USART> {
+                /// Creates the receiver without configuring the USART, e.g. for `static` items
+                ///
+                /// # Safety
+                ///
+                /// The USART must already be configured for reception and the
+                /// receiver must not be in use anywhere else.
+                pub const unsafe fn new_unchecked() -> Self {
+                    Rx {
+                        usart: $USART::ptr(),
+                        _instance: PhantomData,
+                    }
+                }
             }
 
             impl<TXPIN> Serial<$USART, TXPIN, ()>
@@ -308,11 +635,13 @@ macro_rules! usart {
                 TXPIN: TxPin<$USART>,
             {
                 /// Creates a new tx-only serial instance
-                pub fn $usarttx(usart: $USART, txpin: TXPIN, baud_rate: Bps, rcc: &mut Rcc) -> Self
+                pub fn $usarttx<C>(usart: $USART, txpin: TXPIN, config: C, rcc: &mut Rcc) -> Self
+                where
+                    C: Into<Config>,
                 {
                     let rxpin = ();
                     let mut serial = Serial { usart, pins: (txpin, rxpin) };
-                    serial.configure(baud_rate, rcc);
+                    serial.configure(config.into(), rcc);
                     // Enable transmission
                     serial.usart.cr1.modify(|_, w| w.te().set_bit().ue().set_bit());
                     serial
@@ -324,11 +653,13 @@ macro_rules! usart {
                 RXPIN: RxPin<$USART>,
             {
                 /// Creates a new rx-only serial instance
-                pub fn $usartrx(usart: $USART, rxpin: RXPIN, baud_rate: Bps, rcc: &mut Rcc) -> Self
+                pub fn $usartrx<C>(usart: $USART, rxpin: RXPIN, config: C, rcc: &mut Rcc) -> Self
+                where
+                    C: Into<Config>,
                 {
                     let txpin = ();
                     let mut serial = Serial { usart, pins: (txpin, rxpin) };
-                    serial.configure(baud_rate, rcc);
+                    serial.configure(config.into(), rcc);
                     // Enable receiving
                     serial.usart.cr1.modify(|_, w| w.re().set_bit().ue().set_bit());
                     serial
@@ -336,17 +667,51 @@ macro_rules! usart {
             }
 
             impl<TXPIN, RXPIN> Serial<$USART, TXPIN, RXPIN> {
-                fn configure(&mut self, baud_rate: Bps, rcc: &mut Rcc) {
+                fn configure(&mut self, config: Config, rcc: &mut Rcc) {
                     // Enable clock for USART
                     rcc.regs.$apbenr.modify(|_, w| w.$usartXen().set_bit());
+                    clock_event!(enabled, stringify!($USART));
 
                     // Calculate correct baudrate divisor on the fly
-                    let brr = rcc.clocks.pclk().0 / baud_rate.0;
+                    let brr = rcc.clocks.pclk().0 / config.baudrate.0;
                     self.usart.brr.write(|w| unsafe { w.bits(brr) });
 
                     // Reset other registers to disable advanced USART features
                     self.usart.cr2.reset();
                     self.usart.cr3.reset();
+
+                    let stop = match config.stopbits {
+                        StopBits::STOP1 => 0b00,
+                        StopBits::STOP2 => 0b10,
+                        StopBits::STOP1P5 => 0b11,
+                    };
+                    self.usart.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CR2_STOP_MASK) | (stop << CR2_STOP_SHIFT))
+                    });
+
+                    let mut cr1 = 0;
+                    match config.wordlength {
+                        #[cfg(any(
+                            feature = "stm32f042",
+                            feature = "stm32f048",
+                            feature = "stm32f071",
+                            feature = "stm32f072",
+                            feature = "stm32f078",
+                            feature = "stm32f091",
+                            feature = "stm32f098",
+                        ))]
+                        WordLength::DataBits7 => cr1 |= CR1_M1,
+                        WordLength::DataBits8 => {}
+                        WordLength::DataBits9 => cr1 |= CR1_M0,
+                    }
+                    match config.parity {
+                        Parity::ParityNone => {}
+                        Parity::ParityEven => cr1 |= CR1_PCE,
+                        Parity::ParityOdd => cr1 |= CR1_PCE | CR1_PS,
+                    }
+                    self.usart.cr1.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(CR1_M1 | CR1_M0 | CR1_PCE | CR1_PS)) | cr1)
+                    });
                 }
 
                 /// Starts listening for an interrupt event
@@ -379,6 +744,20 @@ macro_rules! usart {
                     }
                 }
 
+                /// Enables or disables the overrun detection (OVRDIS)
+                ///
+                /// Without overrun detection a received byte overwrites the unread
+                /// one instead of setting ORE and blocking the receiver, e.g. for DMA
+                /// streams which tolerate losing data. `read` then never reports
+                /// [Error::Overrun], framing, noise and parity errors are still
+                /// returned. The USART is briefly disabled to change the setting.
+                pub fn set_overrun_detection(&mut self, enabled: bool) {
+                    let ue = self.usart.cr1.read().ue().bit_is_set();
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    self.usart.cr3.modify(|_, w| w.ovrdis().bit(!enabled));
+                    self.usart.cr1.modify(|_, w| w.ue().bit(ue));
+                }
+
                 /// Returns true if the line idle status is set
                 pub fn is_idle(&self) -> bool {
                     self.usart.isr.read().idle().bit_is_set()
@@ -404,7 +783,7 @@ macro_rules! usart {
 }
 
 usart! {
-    USART1: (usart1, usart1tx, usart1rx, usart1en, apb2enr),
+    USART1: (usart1, try_usart1, usart1_setup, usart1tx, usart1rx, usart1en, apb2enr),
 }
 #[cfg(any(
     feature = "stm32f030x8",
@@ -421,7 +800,7 @@ usart! {
     feature = "stm32f098",
 ))]
 usart! {
-    USART2: (usart2, usart2tx, usart2rx,usart2en, apb1enr),
+    USART2: (usart2, try_usart2, usart2_setup, usart2tx, usart2rx,usart2en, apb1enr),
 }
 #[cfg(any(
     feature = "stm32f030xc",
@@ -433,13 +812,221 @@ usart! {
     feature = "stm32f098",
 ))]
 usart! {
-    USART3: (usart3, usart3tx, usart3rx,usart3en, apb1enr),
-    USART4: (usart4, usart4tx, usart4rx,usart4en, apb1enr),
+    USART3: (usart3, try_usart3, usart3_setup, usart3tx, usart3rx,usart3en, apb1enr),
+    USART4: (usart4, try_usart4, usart4_setup, usart4tx, usart4rx,usart4en, apb1enr),
 }
 #[cfg(any(feature = "stm32f030xc", feature = "stm32f091", feature = "stm32f098"))]
 usart! {
-    USART5: (usart5, usart5tx, usart5rx,usart5en, apb1enr),
-    USART6: (usart6, usart6tx, usart6rx,usart6en, apb2enr),
+    USART5: (usart5, try_usart5, usart5_setup, usart5tx, usart5rx,usart5en, apb1enr),
+    USART6: (usart6, try_usart6, usart6_setup, usart6tx, usart6rx,usart6en, apb2enr),
+}
+
+// Interrupt line of the USARTs with a dedicated interrupt in the NVIC
+macro_rules! usart_interrupt {
+    ($($USART:ident,)+) => {
+        $(
+            impl<TXPIN, RXPIN> Serial<$USART, TXPIN, RXPIN> {
+                /// Returns the interrupt line of the USART
+                pub fn interrupt(&self) -> Interrupt {
+                    Interrupt::$USART
+                }
+
+                /// Returns true if the USART interrupt is pending in the NVIC
+                pub fn is_pending(&self) -> bool {
+                    NVIC::is_pending(Interrupt::$USART)
+                }
+
+                /// Clears a pending USART interrupt in the NVIC
+                pub fn unpend(&self) {
+                    NVIC::unpend(Interrupt::$USART)
+                }
+            }
+        )+
+    };
+}
+
+usart_interrupt! {
+    USART1,
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+usart_interrupt! {
+    USART2,
+}
+
+/// USART sharing its interrupt line with other USARTs
+///
+/// Starting with USART3 all USARTs are wired to a single interrupt
+/// (`USART3_4`, `USART3_6` or `USART3_8` depending on the device). There is no
+/// `Serial` for USART7 and USART8 of the STM32F09x, the variants dispatch to
+/// handlers of USARTs configured through the PAC.
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SharedUsart {
+    Usart3,
+    Usart4,
+    #[cfg(any(feature = "stm32f030xc", feature = "stm32f091", feature = "stm32f098"))]
+    Usart5,
+    #[cfg(any(feature = "stm32f030xc", feature = "stm32f091", feature = "stm32f098"))]
+    Usart6,
+    #[cfg(any(feature = "stm32f091", feature = "stm32f098"))]
+    Usart7,
+    #[cfg(any(feature = "stm32f091", feature = "stm32f098"))]
+    Usart8,
+}
+
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+impl SharedUsart {
+    const ALL: &'static [SharedUsart] = &[
+        SharedUsart::Usart3,
+        SharedUsart::Usart4,
+        #[cfg(any(feature = "stm32f030xc", feature = "stm32f091", feature = "stm32f098"))]
+        SharedUsart::Usart5,
+        #[cfg(any(feature = "stm32f030xc", feature = "stm32f091", feature = "stm32f098"))]
+        SharedUsart::Usart6,
+        #[cfg(any(feature = "stm32f091", feature = "stm32f098"))]
+        SharedUsart::Usart7,
+        #[cfg(any(feature = "stm32f091", feature = "stm32f098"))]
+        SharedUsart::Usart8,
+    ];
+
+    fn regs(self) -> &'static SerialRegisterBlock {
+        // NOTE(unsafe) only used for reads without side effects
+        unsafe {
+            match self {
+                SharedUsart::Usart3 => &*USART3::ptr(),
+                SharedUsart::Usart4 => &*USART4::ptr(),
+                #[cfg(any(feature = "stm32f030xc", feature = "stm32f091", feature = "stm32f098"))]
+                SharedUsart::Usart5 => &*USART5::ptr(),
+                #[cfg(any(feature = "stm32f030xc", feature = "stm32f091", feature = "stm32f098"))]
+                SharedUsart::Usart6 => &*USART6::ptr(),
+                #[cfg(any(feature = "stm32f091", feature = "stm32f098"))]
+                SharedUsart::Usart7 => &*crate::pac::USART7::ptr(),
+                #[cfg(any(feature = "stm32f091", feature = "stm32f098"))]
+                SharedUsart::Usart8 => &*crate::pac::USART8::ptr(),
+            }
+        }
+    }
+
+    /// Returns true if an enabled interrupt event of this USART is pending
+    pub fn is_pending(self) -> bool {
+        let usart = self.regs();
+        let isr = usart.isr.read();
+        let cr1 = usart.cr1.read();
+        let cr3 = usart.cr3.read();
+
+        let rx = isr.rxne().bit_is_set() || isr.ore().bit_is_set();
+        let error = isr.fe().bit_is_set() || isr.nf().bit_is_set() || isr.ore().bit_is_set();
+
+        (rx && cr1.rxneie().bit_is_set())
+            || (isr.txe().bit_is_set() && cr1.txeie().bit_is_set())
+            || (isr.tc().bit_is_set() && cr1.tcie().bit_is_set())
+            || (isr.idle().bit_is_set() && cr1.idleie().bit_is_set())
+            || (isr.pe().bit_is_set() && cr1.peie().bit_is_set())
+            || (error && cr3.eie().bit_is_set())
+    }
+}
+
+/// Dispatcher for the shared USART interrupt
+///
+/// Handlers are registered per USART and called from
+/// [dispatch](SharedIrq::dispatch), which should be invoked from the shared
+/// interrupt handler. Only handlers of USARTs with a pending enabled event are
+/// run.
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+pub struct SharedIrq {
+    handlers: [Option<fn()>; SharedUsart::ALL.len()],
+}
+
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+impl SharedIrq {
+    /// Creates a dispatcher without any handlers
+    pub const fn new() -> Self {
+        SharedIrq {
+            handlers: [None; SharedUsart::ALL.len()],
+        }
+    }
+
+    /// Registers the `handler` for `usart`, replacing a previous one
+    pub fn register(&mut self, usart: SharedUsart, handler: fn()) {
+        self.handlers[usart as usize] = Some(handler);
+    }
+
+    /// Removes the handler of `usart`
+    pub fn unregister(&mut self, usart: SharedUsart) {
+        self.handlers[usart as usize] = None;
+    }
+
+    /// Runs the handlers of all USARTs with pending interrupt events
+    pub fn dispatch(&self) {
+        for &usart in SharedUsart::ALL {
+            if let Some(handler) = self.handlers[usart as usize] {
+                if usart.is_pending() {
+                    handler();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+impl Default for SharedIrq {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<USART> embedded_hal::serial::Read<u8> for Rx<USART>
@@ -504,6 +1091,43 @@ where
     }
 }
 
+impl<USART> Tx<USART>
+where
+    USART: Deref<Target = SerialRegisterBlock>,
+{
+    /// Waits until the transmit register can take the next byte
+    ///
+    /// Unlike `flush` this doesn't wait for the last byte to be shifted out, so
+    /// the line stays busy without gaps when writing more bytes right after.
+    pub fn flush_tx_buffer(&mut self) -> nb::Result<(), Infallible> {
+        flush_tx_buffer(self.usart)
+    }
+
+    /// Writes all `bytes` and waits until the transmission is complete
+    pub fn write_all(&mut self, bytes: &[u8]) {
+        write_all(self.usart, bytes)
+    }
+}
+
+impl<USART, TXPIN, RXPIN> Serial<USART, TXPIN, RXPIN>
+where
+    USART: Deref<Target = SerialRegisterBlock>,
+    TXPIN: TxPin<USART>,
+{
+    /// Waits until the transmit register can take the next byte
+    ///
+    /// Unlike `flush` this doesn't wait for the last byte to be shifted out, so
+    /// the line stays busy without gaps when writing more bytes right after.
+    pub fn flush_tx_buffer(&mut self) -> nb::Result<(), Infallible> {
+        flush_tx_buffer(&*self.usart)
+    }
+
+    /// Writes all `bytes` and waits until the transmission is complete
+    pub fn write_all(&mut self, bytes: &[u8]) {
+        write_all(&*self.usart, bytes)
+    }
+}
+
 impl<USART, TXPIN, RXPIN> Serial<USART, TXPIN, RXPIN>
 where
     USART: Deref<Target = SerialRegisterBlock>,
@@ -530,6 +1154,80 @@ where
     pub fn release(self) -> (USART, (TXPIN, RXPIN)) {
         (self.usart, self.pins)
     }
+
+    /// Writes the state of the USART registers in human readable form
+    ///
+    /// Reading the registers has no side effects, so this can be used to
+    /// collect information for bug reports at any time.
+    pub fn debug_dump<W: Write>(&self, w: &mut W) -> Result {
+        let usart = &*self.usart;
+        crate::dump::register(
+            w,
+            "CR1",
+            usart.cr1.read().bits(),
+            &[
+                (0, "UE"),
+                (1, "UESM"),
+                (2, "RE"),
+                (3, "TE"),
+                (4, "IDLEIE"),
+                (5, "RXNEIE"),
+                (6, "TCIE"),
+                (7, "TXEIE"),
+                (8, "PEIE"),
+                (9, "PS"),
+                (10, "PCE"),
+                (12, "M0"),
+                (15, "OVER8"),
+                (28, "M1"),
+            ],
+        )?;
+        crate::dump::register(
+            w,
+            "CR2",
+            usart.cr2.read().bits(),
+            &[(15, "SWAP"), (19, "MSBFIRST"), (20, "ABREN")],
+        )?;
+        crate::dump::register(
+            w,
+            "CR3",
+            usart.cr3.read().bits(),
+            &[
+                (0, "EIE"),
+                (3, "HDSEL"),
+                (6, "DMAR"),
+                (7, "DMAT"),
+                (8, "RTSE"),
+                (9, "CTSE"),
+                (12, "OVRDIS"),
+                (13, "DDRE"),
+            ],
+        )?;
+        crate::dump::register(w, "BRR", usart.brr.read().bits(), &[])?;
+        crate::dump::register(
+            w,
+            "ISR",
+            usart.isr.read().bits(),
+            &[
+                (0, "PE"),
+                (1, "FE"),
+                (2, "NF"),
+                (3, "ORE"),
+                (4, "IDLE"),
+                (5, "RXNE"),
+                (6, "TC"),
+                (7, "TXE"),
+                (9, "CTSIF"),
+                (10, "CTS"),
+                (11, "RTOF"),
+                (14, "ABRE"),
+                (15, "ABRF"),
+                (16, "BUSY"),
+                (21, "TEACK"),
+                (22, "REACK"),
+            ],
+        )
+    }
 }
 
 impl<USART> Write for Tx<USART>
@@ -558,6 +1256,9 @@ where
 }
 
 /// Ensures that none of the previously written words are still buffered
+///
+/// Waits until the last word has been shifted out completely, e.g. before
+/// switching off the driver of a RS-485 transceiver.
 fn flush(usart: *const SerialRegisterBlock) -> nb::Result<(), Infallible> {
     // NOTE(unsafe) atomic read with no side effects
     let isr = unsafe { (*usart).isr.read() };
@@ -569,6 +1270,28 @@ fn flush(usart: *const SerialRegisterBlock) -> nb::Result<(), Infallible> {
     }
 }
 
+/// Ensures that the transmit data register is empty, the last word may still
+/// be shifted out
+fn flush_tx_buffer(usart: *const SerialRegisterBlock) -> nb::Result<(), Infallible> {
+    // NOTE(unsafe) atomic read with no side effects
+    let isr = unsafe { (*usart).isr.read() };
+
+    if isr.txe().bit_is_set() {
+        Ok(())
+    } else {
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+/// Writes all `bytes` as fast as the UART accepts them, then waits for the
+/// transmission to complete
+fn write_all(usart: *const SerialRegisterBlock, bytes: &[u8]) {
+    for byte in bytes {
+        nb::block!(write(usart, *byte)).ok();
+    }
+    nb::block!(flush(usart)).ok();
+}
+
 /// Tries to write a byte to the UART
 /// Returns `Err(WouldBlock)` if the transmit buffer is full
 fn write(usart: *const SerialRegisterBlock, byte: u8) -> nb::Result<(), Infallible> {
@@ -605,7 +1328,10 @@ fn read(usart: *const SerialRegisterBlock) -> nb::Result<u8, Error> {
         icr.write(|w| w.orecf().set_bit());
         Err(nb::Error::Other(Error::Overrun))
     } else if isr.rxne().bit_is_set() {
-        Ok(unsafe { (*usart).rdr.read().rdr().bits() as u8 })
+        // NOTE(unsafe) atomic read with no side effects
+        let cr1 = unsafe { (*usart).cr1.read().bits() };
+        let rdr = unsafe { (*usart).rdr.read().rdr().bits() };
+        Ok((rdr & data_mask(cr1)) as u8)
     } else {
         Err(nb::Error::WouldBlock)
     }
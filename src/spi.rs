@@ -38,7 +38,7 @@
 //! ```
 
 use core::marker::PhantomData;
-use core::{ops::Deref, ptr};
+use core::{fmt, ops::Deref, ptr};
 
 pub use embedded_hal::spi::{Mode, Phase, Polarity};
 
@@ -61,9 +61,11 @@ use crate::pac::SPI1;
 ))]
 use crate::pac::SPI2;
 
+use cortex_m::interrupt::CriticalSection;
+
 use crate::gpio::*;
 
-use crate::rcc::{Clocks, Rcc};
+use crate::rcc::{BusClock, Clocks, ConfigError, Rcc};
 
 use crate::time::Hertz;
 
@@ -92,25 +94,72 @@ pub struct Spi<SPI, SCKPIN, MISOPIN, MOSIPIN, WIDTH> {
     _width: PhantomData<WIDTH>,
 }
 
+/// Pin usable as SCK of `SPI`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as SCK of `{SPI}`",
+    note = "check that the pin provides this function and is set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait SckPin<SPI> {}
+/// Pin usable as MISO of `SPI`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as MISO of `{SPI}`",
+    note = "check that the pin provides this function and is set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait MisoPin<SPI> {}
+/// Pin usable as MOSI of `SPI`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as MOSI of `{SPI}`",
+    note = "check that the pin provides this function and is set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait MosiPin<SPI> {}
 
+// Erased pins are vouched for by the `unsafe` constructor of `Unchecked`
+impl<SPI, AF> SckPin<SPI> for Unchecked<Pin<Alternate<AF>>> {}
+impl<SPI, AF> SckPin<SPI> for Unchecked<ErasedPin<Alternate<AF>>> {}
+impl<SPI, AF> MisoPin<SPI> for Unchecked<Pin<Alternate<AF>>> {}
+impl<SPI, AF> MisoPin<SPI> for Unchecked<ErasedPin<Alternate<AF>>> {}
+impl<SPI, AF> MosiPin<SPI> for Unchecked<Pin<Alternate<AF>>> {}
+impl<SPI, AF> MosiPin<SPI> for Unchecked<ErasedPin<Alternate<AF>>> {}
+
 macro_rules! spi_pins {
     ($($SPI:ident => {
-        sck => [$($sck:ty),+ $(,)*],
-        miso => [$($miso:ty),+ $(,)*],
-        mosi => [$($mosi:ty),+ $(,)*],
+        sck => [$($sckport:ident::$sck:ident<Alternate<$sckaf:ident>>),+ $(,)*],
+        miso => [$($misoport:ident::$miso:ident<Alternate<$misoaf:ident>>),+ $(,)*],
+        mosi => [$($mosiport:ident::$mosi:ident<Alternate<$mosiaf:ident>>),+ $(,)*],
     })+) => {
         $(
             $(
-                impl SckPin<crate::pac::$SPI> for $sck {}
+                impl SckPin<crate::pac::$SPI> for $sckport::$sck<Alternate<$sckaf>> {}
+
+                impl<MODE> ValidPin<crate::pac::$SPI, signal::Sck> for $sckport::$sck<MODE> {
+                    type Configured = $sckport::$sck<Alternate<$sckaf>>;
+
+                    fn setup(self, cs: &CriticalSection) -> Self::Configured {
+                        self.into_alternate(cs)
+                    }
+                }
             )+
             $(
-                impl MisoPin<crate::pac::$SPI> for $miso {}
+                impl MisoPin<crate::pac::$SPI> for $misoport::$miso<Alternate<$misoaf>> {}
+
+                impl<MODE> ValidPin<crate::pac::$SPI, signal::Miso> for $misoport::$miso<MODE> {
+                    type Configured = $misoport::$miso<Alternate<$misoaf>>;
+
+                    fn setup(self, cs: &CriticalSection) -> Self::Configured {
+                        self.into_alternate(cs)
+                    }
+                }
             )+
             $(
-                impl MosiPin<crate::pac::$SPI> for $mosi {}
+                impl MosiPin<crate::pac::$SPI> for $mosiport::$mosi<Alternate<$mosiaf>> {}
+
+                impl<MODE> ValidPin<crate::pac::$SPI, signal::Mosi> for $mosiport::$mosi<MODE> {
+                    type Configured = $mosiport::$mosi<Alternate<$mosiaf>>;
+
+                    fn setup(self, cs: &CriticalSection) -> Self::Configured {
+                        self.into_alternate(cs)
+                    }
+                }
             )+
         )+
     }
@@ -206,9 +255,58 @@ spi_pins! {
     }
 }
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// SPI peripheral
+///
+/// This allows writing code generic over all SPI peripherals.
+pub trait Instance:
+    sealed::Sealed + BusClock + Deref<Target = crate::pac::spi1::RegisterBlock>
+{
+    /// Enables the clock of the peripheral
+    fn enable_clock(rcc: &mut Rcc);
+
+    /// Disables the clock of the peripheral
+    fn disable_clock(rcc: &mut Rcc);
+}
+
+/// Checks that `speed` is within the range of the SPI baud rate prescaler
+fn check_speed(pclk: Hertz, speed: Hertz) -> Result<(), ConfigError> {
+    if speed.0 == 0 {
+        return Err(ConfigError::FrequencyTooLow);
+    }
+    match pclk.0 / speed.0 {
+        0 => Err(ConfigError::FrequencyTooHigh),
+        1..=256 => Ok(()),
+        _ => Err(ConfigError::FrequencyTooLow),
+    }
+}
+
 macro_rules! spi {
-    ($($SPI:ident: ($spi:ident, $spiXen:ident, $spiXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
+    ($($SPI:ident: ($spi:ident, $try_spi:ident, $spi_setup:ident, $spiXen:ident, $spiXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
         $(
+            impl sealed::Sealed for $SPI {}
+
+            impl Instance for $SPI {
+                fn enable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$spiXen().set_bit());
+                    clock_event!(enabled, stringify!($SPI));
+                }
+
+                fn disable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$spiXen().clear_bit());
+                    clock_event!(disabled, stringify!($SPI));
+                }
+            }
+
+            impl BusClock for $SPI {
+                fn clock(clocks: &Clocks) -> Hertz {
+                    clocks.pclk()
+                }
+            }
+
             impl<SCKPIN, MISOPIN, MOSIPIN> Spi<$SPI, SCKPIN, MISOPIN, MOSIPIN, EightBit> {
                 /// Creates a new spi instance
                 pub fn $spi<F>(
@@ -226,6 +324,7 @@ macro_rules! spi {
                 {
                     /* Enable clock for SPI */
                     rcc.regs.$apbenr.modify(|_, w| w.$spiXen().set_bit());
+                    clock_event!(enabled, stringify!($SPI));
 
                     /* Reset SPI */
                     rcc.regs.$apbrstr.modify(|_, w| w.$spiXrst().set_bit());
@@ -233,13 +332,58 @@ macro_rules! spi {
 
                     Spi::<$SPI, SCKPIN, MISOPIN, MOSIPIN, EightBit> { spi, pins, _width: PhantomData }.spi_init(mode, speed, rcc.clocks).into_8bit_width()
                 }
+
+                /// Creates a new spi instance
+                ///
+                /// Returns an error if `speed` is outside the range of the baud rate prescaler
+                pub fn $try_spi<F>(
+                    spi: $SPI,
+                    pins: (SCKPIN, MISOPIN, MOSIPIN),
+                    mode: Mode,
+                    speed: F,
+                    rcc: &mut Rcc,
+                ) -> Result<Self, ConfigError>
+                where
+                    SCKPIN: SckPin<$SPI>,
+                    MISOPIN: MisoPin<$SPI>,
+                    MOSIPIN: MosiPin<$SPI>,
+                    F: Into<Hertz>,
+                {
+                    let speed = speed.into();
+                    check_speed(rcc.clocks.pclk(), speed)?;
+                    Ok(Self::$spi(spi, pins, mode, speed, rcc))
+                }
+
+                /// Creates a new spi instance from pins in any mode
+                ///
+                /// The pins are switched to the alternate function of their signal.
+                pub fn $spi_setup<SCK, MISO, MOSI, F>(
+                    spi: $SPI,
+                    pins: (SCK, MISO, MOSI),
+                    mode: Mode,
+                    speed: F,
+                    rcc: &mut Rcc,
+                    cs: &CriticalSection,
+                ) -> Self
+                where
+                    SCK: ValidPin<$SPI, signal::Sck, Configured = SCKPIN>,
+                    MISO: ValidPin<$SPI, signal::Miso, Configured = MISOPIN>,
+                    MOSI: ValidPin<$SPI, signal::Mosi, Configured = MOSIPIN>,
+                    SCKPIN: SckPin<$SPI>,
+                    MISOPIN: MisoPin<$SPI>,
+                    MOSIPIN: MosiPin<$SPI>,
+                    F: Into<Hertz>,
+                {
+                    let pins = (pins.0.setup(cs), pins.1.setup(cs), pins.2.setup(cs));
+                    Self::$spi(spi, pins, mode, speed, rcc)
+                }
             }
         )+
     }
 }
 
 spi! {
-    SPI1: (spi1, spi1en, spi1rst, apb2enr, apb2rstr),
+    SPI1: (spi1, try_spi1, spi1_setup, spi1en, spi1rst, apb2enr, apb2rstr),
 }
 #[cfg(any(
     feature = "stm32f030x8",
@@ -256,7 +400,7 @@ spi! {
     feature = "stm32f098",
 ))]
 spi! {
-    SPI2: (spi2, spi2en, spi2rst, apb1enr, apb1rstr),
+    SPI2: (spi2, try_spi2, spi2_setup, spi2en, spi2rst, apb1enr, apb1rstr),
 }
 
 // It's s needed for the impls, but rustc doesn't recognize that
@@ -349,6 +493,60 @@ where
         }
     }
 
+    /// Writes the state of the SPI registers in human readable form
+    ///
+    /// Reading the registers has no side effects, so this can be used to
+    /// collect information for bug reports at any time.
+    pub fn debug_dump<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        crate::dump::register(
+            w,
+            "CR1",
+            self.spi.cr1.read().bits(),
+            &[
+                (0, "CPHA"),
+                (1, "CPOL"),
+                (2, "MSTR"),
+                (6, "SPE"),
+                (7, "LSBFIRST"),
+                (8, "SSI"),
+                (9, "SSM"),
+                (10, "RXONLY"),
+                (13, "CRCEN"),
+                (14, "BIDIOE"),
+                (15, "BIDIMODE"),
+            ],
+        )?;
+        crate::dump::register(
+            w,
+            "CR2",
+            self.spi.cr2.read().bits(),
+            &[
+                (0, "RXDMAEN"),
+                (1, "TXDMAEN"),
+                (2, "SSOE"),
+                (3, "NSSP"),
+                (5, "ERRIE"),
+                (6, "RXNEIE"),
+                (7, "TXEIE"),
+                (12, "FRXTH"),
+            ],
+        )?;
+        crate::dump::register(
+            w,
+            "SR",
+            self.spi.sr.read().bits(),
+            &[
+                (0, "RXNE"),
+                (1, "TXE"),
+                (4, "CRCERR"),
+                (5, "MODF"),
+                (6, "OVR"),
+                (7, "BSY"),
+                (8, "FRE"),
+            ],
+        )
+    }
+
     fn set_send_only(&mut self) {
         self.spi
             .cr1
@@ -442,11 +640,26 @@ where
         // We want to transfer bidirectionally, make sure we're in the correct mode
         self.set_bidi();
 
-        for word in words.iter_mut() {
-            nb::block!(self.check_send())?;
-            self.send_u8(*word);
-            nb::block!(self.check_read())?;
-            *word = self.read_u8();
+        // Make sure we don't continue with an error condition
+        nb::block!(self.check_send())?;
+
+        // Keep the TX FIFO primed instead of waiting for every single word, but never have
+        // more words in flight than the RX FIFO can hold
+        let (mut sent, mut received) = (0, 0);
+        while received < words.len() {
+            if sent < words.len() && sent - received < 4 && self.spi.sr.read().txe().bit_is_set() {
+                self.send_u8(words[sent]);
+                sent += 1;
+            }
+
+            match self.check_read() {
+                Ok(()) => {
+                    words[received] = self.read_u8();
+                    received += 1;
+                }
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
         }
 
         Ok(words)
@@ -497,11 +710,26 @@ where
         // We want to transfer bidirectionally, make sure we're in the correct mode
         self.set_bidi();
 
-        for word in words.iter_mut() {
-            nb::block!(self.check_send())?;
-            self.send_u16(*word);
-            nb::block!(self.check_read())?;
-            *word = self.read_u16();
+        // Make sure we don't continue with an error condition
+        nb::block!(self.check_send())?;
+
+        // Keep the TX FIFO primed instead of waiting for every single word, but never have
+        // more words in flight than the RX FIFO can hold
+        let (mut sent, mut received) = (0, 0);
+        while received < words.len() {
+            if sent < words.len() && sent - received < 2 && self.spi.sr.read().txe().bit_is_set() {
+                self.send_u16(words[sent]);
+                sent += 1;
+            }
+
+            match self.check_read() {
+                Ok(()) => {
+                    words[received] = self.read_u16();
+                    received += 1;
+                }
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
         }
 
         Ok(words)
@@ -1,3 +1,5 @@
+use core::fmt;
+
 /// Bits per second
 #[derive(PartialEq, PartialOrd, Clone, Copy)]
 pub struct Bps(pub u32);
@@ -11,6 +13,71 @@ pub struct KiloHertz(pub u32);
 #[derive(PartialEq, PartialOrd, Clone, Copy)]
 pub struct MegaHertz(pub u32);
 
+/// Duration in microseconds
+#[derive(PartialEq, PartialOrd, Clone, Copy)]
+pub struct MicroSecond(pub u32);
+
+/// Duration in milliseconds
+#[derive(PartialEq, PartialOrd, Clone, Copy)]
+pub struct MilliSecond(pub u32);
+
+/// Duration in seconds
+#[derive(PartialEq, PartialOrd, Clone, Copy)]
+pub struct Second(pub u32);
+
+// Formatting with the unit and overflow checked arithmetic
+macro_rules! unit_impl {
+    ($($T:ident: $unit:expr,)+) => {
+        $(
+            impl $T {
+                /// Multiplies by `rhs`, returns `None` on overflow
+                pub fn checked_mul(self, rhs: u32) -> Option<Self> {
+                    self.0.checked_mul(rhs).map($T)
+                }
+
+                /// Divides by `rhs`, returns `None` if `rhs` is 0
+                pub fn checked_div(self, rhs: u32) -> Option<Self> {
+                    self.0.checked_div(rhs).map($T)
+                }
+
+                /// Multiplies by `rhs`, saturating at `u32::MAX`
+                pub fn saturating_mul(self, rhs: u32) -> Self {
+                    $T(self.0.saturating_mul(rhs))
+                }
+            }
+
+            impl fmt::Display for $T {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "{} {}", self.0, $unit)
+                }
+            }
+
+            impl fmt::Debug for $T {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Display::fmt(self, f)
+                }
+            }
+
+            #[cfg(feature = "defmt")]
+            impl defmt::Format for $T {
+                fn format(&self, f: defmt::Formatter) {
+                    defmt::write!(f, "{=u32} {=str}", self.0, $unit)
+                }
+            }
+        )+
+    };
+}
+
+unit_impl! {
+    Bps: "bps",
+    Hertz: "Hz",
+    KiloHertz: "kHz",
+    MegaHertz: "MHz",
+    MicroSecond: "us",
+    MilliSecond: "ms",
+    Second: "s",
+}
+
 /// Extension trait that adds convenience methods to the `u32` type
 pub trait U32Ext {
     /// Wrap in `Bps`
@@ -24,6 +91,15 @@ pub trait U32Ext {
 
     /// Wrap in `MegaHertz`
     fn mhz(self) -> MegaHertz;
+
+    /// Wrap in `MicroSecond`
+    fn us(self) -> MicroSecond;
+
+    /// Wrap in `MilliSecond`
+    fn ms(self) -> MilliSecond;
+
+    /// Wrap in `Second`
+    fn s(self) -> Second;
 }
 
 impl U32Ext for u32 {
@@ -42,6 +118,18 @@ impl U32Ext for u32 {
     fn mhz(self) -> MegaHertz {
         MegaHertz(self)
     }
+
+    fn us(self) -> MicroSecond {
+        MicroSecond(self)
+    }
+
+    fn ms(self) -> MilliSecond {
+        MilliSecond(self)
+    }
+
+    fn s(self) -> Second {
+        Second(self)
+    }
 }
 
 impl From<KiloHertz> for Hertz {
@@ -61,3 +149,21 @@ impl From<MegaHertz> for KiloHertz {
         KiloHertz(mhz.0 * 1_000)
     }
 }
+
+impl From<MilliSecond> for MicroSecond {
+    fn from(ms: MilliSecond) -> Self {
+        MicroSecond(ms.0 * 1_000)
+    }
+}
+
+impl From<Second> for MicroSecond {
+    fn from(s: Second) -> Self {
+        MicroSecond(s.0 * 1_000_000)
+    }
+}
+
+impl From<Second> for MilliSecond {
+    fn from(s: Second) -> Self {
+        MilliSecond(s.0 * 1_000)
+    }
+}
@@ -28,13 +28,20 @@
 //!     }
 //! });
 //! ```
+use core::fmt;
+use core::marker::PhantomData;
+
 use cortex_m::peripheral::syst::SystClkSource;
-use cortex_m::peripheral::SYST;
+use cortex_m::peripheral::{NVIC, SYST};
 
-use crate::rcc::{Clocks, Rcc};
+pub use crate::capture::Edge;
+use crate::pac::Interrupt;
+use crate::pwm::Channel;
+use crate::rcc::{BusClock, Clocks, ConfigError, Rcc};
 
-use crate::time::Hertz;
-use embedded_hal::timer::{CountDown, Periodic};
+use crate::time::{Hertz, MicroSecond};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::timer::{Cancel, CountDown, Periodic};
 use void::Void;
 
 /// Hardware timers
@@ -43,10 +50,120 @@ pub struct Timer<TIM> {
     tim: TIM,
 }
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// TIM peripheral
+///
+/// This allows writing code generic over all timers.
+pub trait Instance: sealed::Sealed + BusClock {
+    /// Enables the clock of the peripheral
+    fn enable_clock(rcc: &mut Rcc);
+
+    /// Disables the clock of the peripheral
+    fn disable_clock(rcc: &mut Rcc);
+}
+
+/// Timer used as a delay provider
+///
+/// This leaves the SysTick free, e.g. for the tick of an RTOS.
+pub struct Delay<TIM> {
+    timer: Timer<TIM>,
+}
+
+/// Free running timer handing out timestamps, e.g. for latency measurements
+///
+/// The counter wraps around after 65536 ticks, measured durations have to be
+/// shorter than that.
+pub struct MonoTimer<TIM> {
+    timer: Timer<TIM>,
+    frequency: Hertz,
+}
+
+/// Timestamp taken from a [MonoTimer]
+pub struct Instant<TIM> {
+    now: u16,
+    _tim: PhantomData<TIM>,
+}
+
+// Not derived, which would require the timer peripheral to be `Copy`
+impl<TIM> Clone for Instant<TIM> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<TIM> Copy for Instant<TIM> {}
+
+/// Copyable delay provider sharing the counter of a [Delay]
+///
+/// Created by `Delay::into_shared`. Each copy can also be used as its own
+/// periodic count down with microsecond resolution.
+pub struct DelayToken<TIM> {
+    last: u16,
+    elapsed: u32,
+    period: u32,
+    _tim: PhantomData<TIM>,
+}
+
+// Not derived, which would require the timer peripheral to be `Copy`
+impl<TIM> Clone for DelayToken<TIM> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<TIM> Copy for DelayToken<TIM> {}
+
+/// Timer error
+#[non_exhaustive]
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The timer is not running
+    Disabled,
+}
+
 /// Interrupt events
+///
+/// The compare events are only available on the channels the timer has, the
+/// SysTick only supports `TimeOut`. `Commutation` and `Break` are only
+/// available on TIM1, TIM15, TIM16 and TIM17, `Trigger` on TIM1, TIM2, TIM3
+/// and TIM15.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Event {
     /// Timer timed out / count down ended
     TimeOut,
+    /// Counter matched the compare value of channel 1
+    Compare1,
+    /// Counter matched the compare value of channel 2
+    Compare2,
+    /// Counter matched the compare value of channel 3
+    Compare3,
+    /// Counter matched the compare value of channel 4
+    Compare4,
+    /// Capture/compare control bits were updated (COM event)
+    Commutation,
+    /// Trigger input detected an edge
+    Trigger,
+    /// Break input became active
+    Break,
+}
+
+impl Event {
+    /// Returns the bit of the event in DIER and SR
+    fn mask(self) -> u32 {
+        match self {
+            Event::TimeOut => 1 << 0,
+            Event::Compare1 => 1 << 1,
+            Event::Compare2 => 1 << 2,
+            Event::Compare3 => 1 << 3,
+            Event::Compare4 => 1 << 4,
+            Event::Commutation => 1 << 5,
+            Event::Trigger => 1 << 6,
+            Event::Break => 1 << 7,
+        }
+    }
 }
 
 impl Timer<SYST> {
@@ -64,17 +181,49 @@ impl Timer<SYST> {
         timer
     }
 
+    /// Starts the timer with a `timeout` given as duration
+    ///
+    /// Be aware that durations above `2^24` core clock cycles can not be reached
+    pub fn start_duration<T>(&mut self, timeout: T)
+    where
+        T: Into<MicroSecond>,
+    {
+        let rvr = duration_ticks(self.clocks.sysclk().0, timeout.into()) - 1;
+
+        assert!(rvr < (1 << 24));
+
+        self.tim.set_reload(rvr);
+        self.tim.clear_current();
+        self.tim.enable_counter();
+    }
+
+    /// Returns the current counter value, SysTick counts down
+    pub fn counter(&self) -> u32 {
+        SYST::get_current()
+    }
+
+    /// Returns the time since the timer was started or last timed out
+    pub fn micros_since_start(&self) -> MicroSecond {
+        let elapsed = SYST::get_reload() - SYST::get_current();
+        ticks_duration(self.clocks.sysclk().0, elapsed.into())
+    }
+
+    /// Returns the time until the next timeout
+    pub fn remaining(&self) -> MicroSecond {
+        ticks_duration(self.clocks.sysclk().0, SYST::get_current().into())
+    }
+
     /// Starts listening for an `event`
     pub fn listen(&mut self, event: &Event) {
-        match event {
-            Event::TimeOut => self.tim.enable_interrupt(),
+        if let Event::TimeOut = event {
+            self.tim.enable_interrupt()
         }
     }
 
     /// Stops listening for an `event`
     pub fn unlisten(&mut self, event: &Event) {
-        match event {
-            Event::TimeOut => self.tim.disable_interrupt(),
+        if let Event::TimeOut = event {
+            self.tim.disable_interrupt()
         }
     }
 }
@@ -112,10 +261,79 @@ impl CountDown for Timer<SYST> {
 
 impl Periodic for Timer<SYST> {}
 
+impl Cancel for Timer<SYST> {
+    type Error = Error;
+
+    /// Stops the timer, it can be restarted with `start`
+    fn cancel(&mut self) -> Result<(), Error> {
+        if !self.tim.is_counter_enabled() {
+            return Err(Error::Disabled);
+        }
+
+        self.tim.disable_counter();
+        Ok(())
+    }
+}
+
+/// Returns the number of `clock` cycles in `duration`, saturated to `u32::MAX`
+fn duration_ticks(clock: u32, duration: MicroSecond) -> u32 {
+    cast::u32(u64::from(clock) * u64::from(duration.0) / 1_000_000).unwrap_or(u32::MAX)
+}
+
+/// Returns the duration of `ticks` cycles of `clock`
+fn ticks_duration(clock: u32, ticks: u64) -> MicroSecond {
+    MicroSecond((ticks * 1_000_000 / u64::from(clock)) as u32)
+}
+
+/// Busy waits for `us` microseconds of a free running 1 MHz `counter`
+fn busy_wait_us<F: Fn() -> u16>(counter: F, mut us: u32) {
+    // Less than the counter range, so we have some play if there's a
+    // long running interrupt
+    const MAX_US: u32 = 0x8000;
+
+    while us != 0 {
+        let current_us = if us <= MAX_US { us } else { MAX_US };
+        let start = counter();
+        while u32::from(counter().wrapping_sub(start)) < current_us {}
+        us -= current_us;
+    }
+}
+
+/// Checks that a `timeout` can be reached from the timer clock
+fn check_timeout(clocks: &Clocks, timeout: Hertz) -> Result<(), ConfigError> {
+    if timeout.0 == 0 {
+        Err(ConfigError::FrequencyTooLow)
+    } else if timeout.0 > clocks.timclk().0 {
+        Err(ConfigError::FrequencyTooHigh)
+    } else {
+        Ok(())
+    }
+}
+
 macro_rules! timers {
-    ($($TIM:ident: ($tim:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident),)+) => {
+    ($($TIM:ident: ($tim:ident, $try_tim:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident, $W:ty),)+) => {
         $(
             use crate::pac::$TIM;
+
+            impl sealed::Sealed for $TIM {}
+
+            impl Instance for $TIM {
+                fn enable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIM));
+                }
+
+                fn disable_clock(rcc: &mut Rcc) {
+                    rcc.regs.$apbenr.modify(|_, w| w.$timXen().clear_bit());
+                    clock_event!(disabled, stringify!($TIM));
+                }
+            }
+
+            impl BusClock for $TIM {
+                fn clock(clocks: &Clocks) -> Hertz {
+                    clocks.timclk()
+                }
+            }
             impl Timer<$TIM> {
                 // XXX(why not name this `new`?) bummer: constructors need to have different names
                 // even if the `$TIM` are non overlapping (compare to the `free` function below
@@ -127,6 +345,7 @@ macro_rules! timers {
                 {
                     // enable and reset peripheral to a clean slate state
                     rcc.regs.$apbenr.modify(|_, w| w.$timXen().set_bit());
+                    clock_event!(enabled, stringify!($TIM));
                     rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
                     rcc.regs.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
 
@@ -139,24 +358,133 @@ macro_rules! timers {
                     timer
                 }
 
+                /// Configures a TIM peripheral as a periodic count down timer
+                ///
+                /// Returns an error instead of panicking if `timeout` cannot be reached
+                pub fn $try_tim<T>(tim: $TIM, timeout: T, rcc: &mut Rcc) -> Result<Self, ConfigError>
+                where
+                    T: Into<Hertz>,
+                {
+                    let timeout = timeout.into();
+                    check_timeout(&rcc.clocks, timeout)?;
+                    Ok(Self::$tim(tim, timeout, rcc))
+                }
+
                 /// Starts listening for an `event`
                 pub fn listen(&mut self, event: Event) {
-                    match event {
-                        Event::TimeOut => {
-                            // Enable update event interrupt
-                            self.tim.dier.write(|w| w.uie().set_bit());
-                        }
-                    }
+                    // Keep the other enabled interrupts
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() | event.mask()) });
                 }
 
                 /// Stops listening for an `event`
                 pub fn unlisten(&mut self, event: Event) {
-                    match event {
-                        Event::TimeOut => {
-                            // Enable update event interrupt
-                            self.tim.dier.write(|w| w.uie().clear_bit());
-                        }
-                    }
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() & !event.mask()) });
+                }
+
+                /// Enables the DMA request of an `event`
+                ///
+                /// E.g. `TimeOut` paces a transfer with the update rate. There is no
+                /// DMA request for `Break`.
+                pub fn enable_dma(&mut self, event: Event) {
+                    assert!(event != Event::Break);
+                    // The DMA request enables follow the interrupt enables in DIER
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() | (event.mask() << 8)) });
+                }
+
+                /// Disables the DMA request of an `event`
+                pub fn disable_dma(&mut self, event: Event) {
+                    self.tim.dier.modify(|r, w| unsafe { w.bits(r.bits() & !(event.mask() << 8)) });
+                }
+
+                /// Returns true if the flag of an `event` is set
+                ///
+                /// The flags are set whether the event is listened to or not, so
+                /// handlers of interrupts shared by several events can dispatch on them.
+                pub fn is_event_pending(&self, event: Event) -> bool {
+                    self.tim.sr.read().bits() & event.mask() != 0
+                }
+
+                /// Clears the interrupt flag of an `event`
+                pub fn clear(&mut self, event: Event) {
+                    // The flags are cleared by writing 0, writing 1 has no effect
+                    self.tim.sr.write(|w| unsafe { w.bits(0xffff & !event.mask()) });
+                }
+
+                /// Sets the prescaler, the counter is incremented every `psc + 1` timer
+                /// clock cycles
+                ///
+                /// The new value is used after the next update event.
+                pub fn set_prescaler(&mut self, psc: u16) {
+                    self.tim.psc.write(|w| w.psc().bits(psc));
+                }
+
+                /// Sets the auto-reload value, the timer times out after `arr + 1` ticks
+                ///
+                /// The value is 32 bit wide on TIM2 and 16 bit on the other timers.
+                #[allow(unused_unsafe)]
+                pub fn set_auto_reload(&mut self, arr: $W) {
+                    self.tim.arr.write(|w| unsafe { w.bits(u32::from(arr)) });
+                }
+
+                /// Generates an update event
+                ///
+                /// This reloads the prescaler, restarts the counter and raises a
+                /// [Event::TimeOut].
+                pub fn generate_update(&mut self) {
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                }
+
+                /// Returns the current counter value
+                pub fn counter(&self) -> u32 {
+                    self.tim.cnt.read().bits()
+                }
+
+                /// Returns the time since the timer was started or last timed out
+                pub fn micros_since_start(&self) -> MicroSecond {
+                    self.ticks_duration(self.counter())
+                }
+
+                /// Returns the time until the next timeout
+                pub fn remaining(&self) -> MicroSecond {
+                    let arr = self.tim.arr.read().bits();
+                    self.ticks_duration(arr.saturating_sub(self.counter()))
+                }
+
+                fn ticks_duration(&self, ticks: u32) -> MicroSecond {
+                    let psc = u64::from(self.tim.psc.read().psc().bits()) + 1;
+                    ticks_duration(self.clocks.timclk().0, u64::from(ticks) * psc)
+                }
+
+                /// Starts the timer with a `timeout` given as duration
+                ///
+                /// Unlike `start` this is not limited to whole frequencies, so e.g. 1.5ms
+                /// or 30s timeouts are possible. The timeout is clamped to between one
+                /// and `2^32 - 1` timer clock cycles, about 89s at 48MHz.
+                #[allow(unused_unsafe)]
+                pub fn start_duration<T>(&mut self, timeout: T)
+                where
+                    T: Into<MicroSecond>,
+                {
+                    // pause
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    // restart counter
+                    self.tim.cnt.reset();
+
+                    let ticks = duration_ticks(self.clocks.timclk().0, timeout.into()).max(1);
+
+                    let psc = cast::u16((ticks - 1) / (1 << 16)).unwrap();
+                    self.tim.psc.write(|w| w.psc().bits(psc));
+
+                    let arr = cast::u16(ticks / cast::u32(psc + 1) - 1).unwrap();
+                    self.tim.arr.write(|w| unsafe { w.bits(cast::u32(arr)) });
+
+                    // Load the prescaler without raising a timeout
+                    self.tim.cr1.modify(|_, w| w.urs().set_bit());
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    // start counter
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
                 }
 
                 /// Releases the TIM peripheral
@@ -166,6 +494,7 @@ macro_rules! timers {
                     self.tim.cr1.modify(|_, w| w.cen().clear_bit());
                     // Disable timer
                     rcc.$apbenr.modify(|_, w| w.$timXen().clear_bit());
+                    clock_event!(disabled, stringify!($TIM));
                     self.tim
                 }
 
@@ -173,6 +502,57 @@ macro_rules! timers {
                 pub fn clear_irq(&mut self) {
                     self.tim.sr.modify(|_, w| w.uif().clear_bit());
                 }
+
+                /// Writes the state of the timer registers in human readable form
+                ///
+                /// Reading the registers has no side effects, so this can be used to
+                /// collect information for bug reports at any time.
+                pub fn debug_dump<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+                    crate::dump::register(
+                        w,
+                        "CR1",
+                        self.tim.cr1.read().bits(),
+                        &[(0, "CEN"), (1, "UDIS"), (2, "URS"), (3, "OPM"), (4, "DIR"), (7, "ARPE")],
+                    )?;
+                    crate::dump::register(
+                        w,
+                        "DIER",
+                        self.tim.dier.read().bits(),
+                        &[
+                            (0, "UIE"),
+                            (1, "CC1IE"),
+                            (2, "CC2IE"),
+                            (3, "CC3IE"),
+                            (4, "CC4IE"),
+                            (5, "COMIE"),
+                            (6, "TIE"),
+                            (7, "BIE"),
+                            (8, "UDE"),
+                        ],
+                    )?;
+                    crate::dump::register(
+                        w,
+                        "SR",
+                        self.tim.sr.read().bits(),
+                        &[
+                            (0, "UIF"),
+                            (1, "CC1IF"),
+                            (2, "CC2IF"),
+                            (3, "CC3IF"),
+                            (4, "CC4IF"),
+                            (5, "COMIF"),
+                            (6, "TIF"),
+                            (7, "BIF"),
+                            (9, "CC1OF"),
+                            (10, "CC2OF"),
+                            (11, "CC3OF"),
+                            (12, "CC4OF"),
+                        ],
+                    )?;
+                    crate::dump::register(w, "CNT", self.tim.cnt.read().bits(), &[])?;
+                    crate::dump::register(w, "PSC", self.tim.psc.read().bits(), &[])?;
+                    crate::dump::register(w, "ARR", self.tim.arr.read().bits(), &[])
+                }
             }
 
             impl CountDown for Timer<$TIM> {
@@ -183,19 +563,58 @@ macro_rules! timers {
                 where
                     T: Into<Hertz>,
                 {
+                    self.start_counter(timeout.into());
+                    // Back to periodic operation after `start_once`, written raw as TIM14
+                    // has no OPM bit, it is reserved there and stays 0
+                    self.tim.cr1.modify(|r, w| unsafe { w.bits(r.bits() & !CR1_OPM) });
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                /// Return `Ok` if the timer has wrapped
+                /// Automatically clears the flag and restarts the time
+                fn wait(&mut self) -> nb::Result<(), Void> {
+                    if self.tim.sr.read().uif().bit_is_clear() {
+                        Err(nb::Error::WouldBlock)
+                    } else {
+                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                        Ok(())
+                    }
+                }
+            }
+
+            impl Periodic for Timer<$TIM> {}
+
+            impl Timer<$TIM> {
+                /// Changes the timeout of the running timer without stopping it
+                ///
+                /// Prescaler and auto-reload are buffered and take effect together at
+                /// the next update event, so the current period completes with the old
+                /// timeout.
+                #[allow(unused_unsafe)]
+                pub fn set_timeout<T>(&mut self, timeout: T)
+                where
+                    T: Into<Hertz>,
+                {
+                    let ticks = self.clocks.timclk().0 / timeout.into().0;
+                    let psc = cast::u16((ticks - 1) / (1 << 16)).unwrap();
+                    let arr = cast::u16(ticks / cast::u32(psc + 1)).unwrap();
+
+                    // Buffer ARR like PSC, so both switch on the same update event
+                    self.tim.cr1.modify(|_, w| w.arpe().set_bit());
+                    self.tim.psc.write(|w| w.psc().bits(psc));
+                    self.tim.arr.write(|w| unsafe { w.bits(cast::u32(arr)) });
+                }
+
+                /// Loads `timeout` into the paused timer, the caller starts the counter
+                #[allow(unused_unsafe)]
+                fn start_counter(&mut self, timeout: Hertz) {
                     // pause
                     self.tim.cr1.modify(|_, w| w.cen().clear_bit());
                     // restart counter
                     self.tim.cnt.reset();
 
-                    let frequency = timeout.into().0;
-                    // If pclk is prescaled from hclk, the frequency fed into the timers is doubled
-                    let tclk = if self.clocks.hclk().0 == self.clocks.pclk().0 {
-                        self.clocks.pclk().0
-                    } else {
-                        self.clocks.pclk().0 * 2
-                    };
-                    let ticks = tclk / frequency;
+                    let frequency = timeout.0;
+                    let ticks = self.clocks.timclk().0 / frequency;
 
                     let psc = cast::u16((ticks - 1) / (1 << 16)).unwrap();
                     self.tim.psc.write(|w| w.psc().bits(psc));
@@ -203,33 +622,850 @@ macro_rules! timers {
                     let arr = cast::u16(ticks / cast::u32(psc + 1)).unwrap();
                     self.tim.arr.write(|w| unsafe { w.bits(cast::u32(arr)) });
 
-                    // start counter
-                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                    // Load prescaler and auto-reload without raising a timeout, ARR is
+                    // buffered after set_timeout
+                    self.tim.cr1.modify(|_, w| w.urs().set_bit());
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.tim.cr1.modify(|_, w| w.urs().clear_bit());
+                }
+            }
+
+            impl Timer<$TIM> {
+                /// Turns the timer into a delay provider with microsecond resolution
+                #[allow(unused_unsafe)]
+                pub fn delay(self) -> Delay<$TIM> {
+                    let tim = &self.tim;
+                    // pause
+                    tim.cr1.modify(|_, w| w.cen().clear_bit());
+
+                    // Count microseconds and wrap around at 0xffff
+                    assert!(self.clocks.timclk().0 >= 1_000_000);
+                    let psc = cast::u16(self.clocks.timclk().0 / 1_000_000 - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| unsafe { w.bits(0xffff) });
+
+                    // Load the prescaler without raising a timeout
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    Delay { timer: self }
+                }
+            }
+
+            impl Timer<$TIM> {
+                /// Turns the timer into a free running timestamp clock counting with
+                /// `frequency`
+                ///
+                /// The frequency is rounded to a whole divider of the timer clock, see
+                /// [MonoTimer::frequency].
+                #[allow(unused_unsafe)]
+                pub fn mono_timer<T>(self, frequency: T) -> MonoTimer<$TIM>
+                where
+                    T: Into<Hertz>,
+                {
+                    let tim = &self.tim;
+                    // pause
+                    tim.cr1.modify(|_, w| w.cen().clear_bit());
+
+                    let timclk = self.clocks.timclk().0;
+                    let psc = cast::u16(timclk / frequency.into().0 - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.arr.write(|w| unsafe { w.bits(0xffff) });
+
+                    // Load the prescaler without raising a timeout
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    MonoTimer {
+                        frequency: Hertz(timclk / (u32::from(psc) + 1)),
+                        timer: self,
+                    }
+                }
+            }
+
+            impl Delay<$TIM> {
+                /// Releases the timer
+                pub fn release(self) -> Timer<$TIM> {
+                    self.timer
+                }
+            }
+
+            impl MonoTimer<$TIM> {
+                /// Returns the frequency the timer counts with
+                pub fn frequency(&self) -> Hertz {
+                    self.frequency
+                }
+
+                /// Returns the current timestamp
+                pub fn now(&self) -> Instant<$TIM> {
+                    Instant {
+                        now: self.timer.tim.cnt.read().bits() as u16,
+                        _tim: PhantomData,
+                    }
+                }
+
+                /// Releases the timer
+                pub fn release(self) -> Timer<$TIM> {
+                    self.timer
+                }
+            }
+
+            impl Instant<$TIM> {
+                /// Returns the ticks elapsed since the timestamp was taken
+                pub fn elapsed(&self) -> u16 {
+                    //NOTE(unsafe) atomic read with no side effects
+                    let cnt = unsafe { (*$TIM::ptr()).cnt.read().bits() as u16 };
+                    cnt.wrapping_sub(self.now)
+                }
+            }
+
+            impl DelayUs<u32> for Delay<$TIM> {
+                fn delay_us(&mut self, us: u32) {
+                    let tim = &self.timer.tim;
+                    busy_wait_us(|| tim.cnt.read().bits() as u16, us);
+                }
+            }
+
+            impl DelayUs<u16> for Delay<$TIM> {
+                fn delay_us(&mut self, us: u16) {
+                    self.delay_us(u32::from(us))
+                }
+            }
+
+            impl DelayUs<u8> for Delay<$TIM> {
+                fn delay_us(&mut self, us: u8) {
+                    self.delay_us(u32::from(us))
+                }
+            }
+
+            impl DelayMs<u32> for Delay<$TIM> {
+                fn delay_ms(&mut self, mut ms: u32) {
+                    const MAX_MS: u32 = 1_000;
+                    while ms != 0 {
+                        let current_ms = if ms <= MAX_MS { ms } else { MAX_MS };
+                        self.delay_us(current_ms * 1_000);
+                        ms -= current_ms;
+                    }
+                }
+            }
+
+            impl DelayMs<u16> for Delay<$TIM> {
+                fn delay_ms(&mut self, ms: u16) {
+                    self.delay_ms(u32::from(ms))
+                }
+            }
+
+            impl DelayMs<u8> for Delay<$TIM> {
+                fn delay_ms(&mut self, ms: u8) {
+                    self.delay_ms(u32::from(ms))
+                }
+            }
+
+            impl Delay<$TIM> {
+                /// Turns the delay into a token which can be copied into several
+                /// drivers
+                ///
+                /// The tokens only read the free running counter, so they can be used
+                /// from interrupts and the main loop at the same time. The timer can't
+                /// be released anymore.
+                pub fn into_shared(self) -> DelayToken<$TIM> {
+                    DelayToken {
+                        last: 0,
+                        elapsed: 0,
+                        period: 0,
+                        _tim: PhantomData,
+                    }
+                }
+            }
+
+            impl DelayToken<$TIM> {
+                fn now() -> u16 {
+                    //NOTE(unsafe) atomic read with no side effects
+                    unsafe { (*$TIM::ptr()).cnt.read().bits() as u16 }
+                }
+            }
+
+            impl DelayUs<u32> for DelayToken<$TIM> {
+                fn delay_us(&mut self, us: u32) {
+                    busy_wait_us(Self::now, us);
+                }
+            }
+
+            impl DelayUs<u16> for DelayToken<$TIM> {
+                fn delay_us(&mut self, us: u16) {
+                    self.delay_us(u32::from(us))
+                }
+            }
+
+            impl DelayMs<u32> for DelayToken<$TIM> {
+                fn delay_ms(&mut self, mut ms: u32) {
+                    const MAX_MS: u32 = 1_000;
+                    while ms != 0 {
+                        let current_ms = if ms <= MAX_MS { ms } else { MAX_MS };
+                        busy_wait_us(Self::now, current_ms * 1_000);
+                        ms -= current_ms;
+                    }
+                }
+            }
+
+            impl DelayMs<u16> for DelayToken<$TIM> {
+                fn delay_ms(&mut self, ms: u16) {
+                    self.delay_ms(u32::from(ms))
+                }
+            }
+
+            impl CountDown for DelayToken<$TIM> {
+                type Time = MicroSecond;
+
+                fn start<T>(&mut self, timeout: T)
+                where
+                    T: Into<MicroSecond>,
+                {
+                    self.period = timeout.into().0;
+                    self.elapsed = 0;
+                    self.last = Self::now();
+                }
+
+                /// Has to be polled at least every 65 ms, as the counter wraps around
+                fn wait(&mut self) -> nb::Result<(), Void> {
+                    let now = Self::now();
+                    self.elapsed += u32::from(now.wrapping_sub(self.last));
+                    self.last = now;
+                    if self.elapsed < self.period {
+                        Err(nb::Error::WouldBlock)
+                    } else {
+                        self.elapsed -= self.period;
+                        Ok(())
+                    }
+                }
+            }
+
+            impl Periodic for DelayToken<$TIM> {}
+
+            impl Cancel for Timer<$TIM> {
+                type Error = Error;
+
+                /// Stops the timer, it can be restarted with `start`
+                fn cancel(&mut self) -> Result<(), Error> {
+                    if self.tim.cr1.read().cen().bit_is_clear() {
+                        return Err(Error::Disabled);
+                    }
+
+                    // pause and discard a pending timeout
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                    Ok(())
+                }
+            }
+        )+
+    }
+}
+
+// One pulse mode, which TIM14 doesn't have
+macro_rules! one_shot {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Starts the timer to time out once after `timeout`
+                ///
+                /// The counter stops at the timeout, so `wait` only completes a single
+                /// time. Use `start` to return to periodic operation.
+                pub fn start_once<T>(&mut self, timeout: T)
+                where
+                    T: Into<Hertz>,
+                {
+                    self.start_counter(timeout.into());
+                    // in one pulse mode the counter stops at the next update event
+                    self.tim.cr1.modify(|_, w| w.opm().set_bit().cen().set_bit());
+                }
+            }
+        )+
+    }
+}
+
+timers! {
+    TIM1: (tim1, try_tim1, tim1en, tim1rst, apb2enr, apb2rstr, u16),
+    TIM3: (tim3, try_tim3, tim3en, tim3rst, apb1enr, apb1rstr, u16),
+    TIM14: (tim14, try_tim14, tim14en, tim14rst, apb1enr, apb1rstr, u16),
+    TIM16: (tim16, try_tim16, tim16en, tim16rst, apb2enr, apb2rstr, u16),
+    TIM17: (tim17, try_tim17, tim17en, tim17rst, apb2enr, apb2rstr, u16),
+}
+
+one_shot! {
+    TIM1,
+    TIM3,
+    TIM16,
+    TIM17,
+}
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+timers! {
+    TIM2: (tim2, try_tim2, tim2en, tim2rst, apb1enr, apb1rstr, u32),
+}
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+one_shot! {
+    TIM2,
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+timers! {
+    TIM6: (tim6, try_tim6, tim6en, tim6rst, apb1enr, apb1rstr, u16),
+    TIM15: (tim15, try_tim15, tim15en, tim15rst, apb2enr, apb2rstr, u16),
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+one_shot! {
+    TIM6,
+    TIM15,
+}
+
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+timers! {
+    TIM7: (tim7, try_tim7, tim7en, tim7rst, apb1enr, apb1rstr, u16),
+}
+
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+one_shot! {
+    TIM7,
+}
+
+/// Trigger output (TRGO) of a master timer
+///
+/// Besides other timers, the TRGO of TIM1, TIM2, TIM3 and TIM15 can start ADC
+/// conversions, see [AdcTrigger](crate::adc::AdcTrigger).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MasterMode {
+    /// Setting the UG bit
+    Reset,
+    /// Enabling the counter
+    Enable,
+    /// Update event
+    Update,
+    /// Capture/compare 1 flag being set
+    ComparePulse,
+    /// OC1REF signal
+    Compare1,
+    /// OC2REF signal
+    Compare2,
+    /// OC3REF signal
+    Compare3,
+    /// OC4REF signal
+    Compare4,
+}
+
+/// Reaction of a slave timer to its trigger input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlaveMode {
+    /// Trigger input is ignored
+    Disabled,
+    /// Rising edges reset the counter
+    Reset,
+    /// Counter runs while the trigger input is high
+    Gated,
+    /// Rising edges start the counter
+    Trigger,
+    /// Rising edges clock the counter
+    ExternalClock,
+}
+
+/// Internal trigger input of a slave timer
+///
+/// The timer connected to each input depends on the slave timer, e.g. `Itr0`
+/// of TIM3 is TIM1. See the "TIMx internal trigger connection" tables of the
+/// reference manual.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerSource {
+    /// Internal trigger 0
+    Itr0,
+    /// Internal trigger 1
+    Itr1,
+    /// Internal trigger 2
+    Itr2,
+    /// Internal trigger 3
+    Itr3,
+}
+
+/// External input controlling a slave timer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlaveInput {
+    /// Channel 1 input (TI1FP1)
+    Ti1,
+    /// Channel 2 input (TI2FP2)
+    Ti2,
+    /// Both edges of the channel 1 input (TI1F_ED)
+    Ti1BothEdges,
+    /// External trigger input (ETRF), not available on TIM15
+    Etr,
+}
+
+impl SlaveInput {
+    fn ts_bits(self) -> u32 {
+        match self {
+            SlaveInput::Ti1BothEdges => 0b100,
+            SlaveInput::Ti1 => 0b101,
+            SlaveInput::Ti2 => SMCR_TS_TI2FP2,
+            SlaveInput::Etr => 0b111,
+        }
+    }
+}
+
+// CR1 fields
+const CR1_OPM: u32 = 1 << 3;
+
+// CR2 fields
+const CR2_MMS_SHIFT: u32 = 4;
+const CR2_MMS_MASK: u32 = 0b111 << CR2_MMS_SHIFT;
+
+// SMCR fields
+const SMCR_SMS_TRIGGER: u32 = 0b110;
+const SMCR_TS_SHIFT: u32 = 4;
+const SMCR_TS_TI2FP2: u32 = 0b110;
+const SMCR_MASK: u32 = 0b111 | (0b111 << SMCR_TS_SHIFT);
+const SMCR_ETP: u32 = 1 << 15;
+
+impl MasterMode {
+    fn mms_bits(self) -> u32 {
+        match self {
+            MasterMode::Reset => 0b000,
+            MasterMode::Enable => 0b001,
+            MasterMode::Update => 0b010,
+            MasterMode::ComparePulse => 0b011,
+            MasterMode::Compare1 => 0b100,
+            MasterMode::Compare2 => 0b101,
+            MasterMode::Compare3 => 0b110,
+            MasterMode::Compare4 => 0b111,
+        }
+    }
+}
+
+impl SlaveMode {
+    fn sms_bits(self) -> u32 {
+        match self {
+            SlaveMode::Disabled => 0b000,
+            SlaveMode::Reset => 0b100,
+            SlaveMode::Gated => 0b101,
+            SlaveMode::Trigger => SMCR_SMS_TRIGGER,
+            SlaveMode::ExternalClock => 0b111,
+        }
+    }
+}
+
+impl TriggerSource {
+    fn ts_bits(self) -> u32 {
+        match self {
+            TriggerSource::Itr0 => 0b000,
+            TriggerSource::Itr1 => 0b001,
+            TriggerSource::Itr2 => 0b010,
+            TriggerSource::Itr3 => 0b011,
+        }
+    }
+}
+
+macro_rules! master_mode {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Selects the event driving the trigger output (TRGO)
+                pub fn set_master_mode(&mut self, mode: MasterMode) {
+                    self.tim.cr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !CR2_MMS_MASK) | (mode.mms_bits() << CR2_MMS_SHIFT))
+                    });
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! slave_mode {
+    ($($TIM:ident: $etr:expr,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Controls the counter from the trigger output of another timer
+                ///
+                /// Selecting [SlaveMode::Trigger] stops the counter, so it is started by
+                /// the next trigger.
+                pub fn set_slave_mode(&mut self, mode: SlaveMode, source: TriggerSource) {
+                    if mode == SlaveMode::Trigger {
+                        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    }
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !SMCR_MASK)
+                                | mode.sms_bits()
+                                | (source.ts_bits() << SMCR_TS_SHIFT),
+                        )
+                    });
+                }
+
+                /// Controls the counter from an external input
+                ///
+                /// `edge` selects the active edge, in [SlaveMode::Gated] the counter
+                /// runs while the input is high for `Rising` and low for `Falling`.
+                /// `Both` must not be used in gated mode and acts like `Rising` on
+                /// [SlaveInput::Etr]. [SlaveInput::Ti1BothEdges] ignores it. The pin of the input has to
+                /// be in its timer alternate function, and the channel of `Ti1` and
+                /// `Ti2` can't be used for anything else. Panics for `Etr` on TIM15,
+                /// which has no external trigger input.
+                pub fn set_slave_input(&mut self, mode: SlaveMode, input: SlaveInput, edge: Edge) {
+                    assert!($etr || input != SlaveInput::Etr, "the timer has no ETR input");
+                    let inverted = edge != Edge::Rising;
+                    let both = edge == Edge::Both;
+                    match input {
+                        SlaveInput::Ti1 | SlaveInput::Ti1BothEdges => {
+                            // CC1 as input on TI1
+                            self.tim.ccer.modify(|_, w| w.cc1e().clear_bit());
+                            self.tim
+                                .ccmr1_input()
+                                .modify(|r, w| unsafe { w.bits((r.bits() & !0xff) | 0b01) });
+                            self.tim
+                                .ccer
+                                .modify(|_, w| w.cc1p().bit(inverted).cc1np().bit(both));
+                        }
+                        SlaveInput::Ti2 => {
+                            // CC2 as input on TI2
+                            self.tim.ccer.modify(|_, w| w.cc2e().clear_bit());
+                            self.tim
+                                .ccmr1_input()
+                                .modify(|r, w| unsafe { w.bits((r.bits() & !(0xff << 8)) | (0b01 << 8)) });
+                            self.tim
+                                .ccer
+                                .modify(|_, w| w.cc2p().bit(inverted).cc2np().bit(both));
+                        }
+                        SlaveInput::Etr => {}
+                    }
+
+                    if mode == SlaveMode::Trigger {
+                        self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    }
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        let mut bits = (r.bits() & !(SMCR_MASK | SMCR_ETP))
+                            | mode.sms_bits()
+                            | (input.ts_bits() << SMCR_TS_SHIFT);
+                        if input == SlaveInput::Etr && edge == Edge::Falling {
+                            bits |= SMCR_ETP;
+                        }
+                        w.bits(bits)
+                    });
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! repetition_counter {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Sets the repetition counter, an update event is generated every
+                /// `rep + 1` counter overflows
+                ///
+                /// The new value is used after the next update event, see
+                /// [Timer::generate_update] to apply it immediately.
+                #[allow(unused_unsafe)]
+                pub fn set_repetition_counter(&mut self, rep: u8) {
+                    // REP is only marked safe in the PAC of some timers
+                    self.tim.rcr.write(|w| unsafe { w.rep().bits(rep) });
+                }
+
+                /// Returns the configured repetition counter
+                pub fn repetition_counter(&self) -> u8 {
+                    self.tim.rcr.read().rep().bits()
+                }
+            }
+        )+
+    };
+}
+
+// Compare channels used without output, e.g. as software alarms
+macro_rules! compare_channels {
+    ($($TIM:ident: $W:ty, [$($C:ident => $ccrx:ident),+],)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Sets the compare value of a `channel` which is not routed to a pin
+                ///
+                /// Together with the compare events this schedules several alarms
+                /// within one timer period. The channel must not be in use for PWM or
+                /// input capture. Panics if the channel does not exist on the timer.
+                #[allow(unused_unsafe)]
+                pub fn set_compare(&mut self, channel: Channel, value: $W) {
+                    match channel {
+                        $(Channel::$C => self.tim.$ccrx().write(|w| unsafe { w.bits(u32::from(value)) }),)+
+                        #[allow(unreachable_patterns)]
+                        _ => panic!("channel not available on this timer"),
+                    }
+                }
+
+                /// Returns the compare value of a `channel`
+                ///
+                /// Panics if the channel does not exist on the timer.
+                pub fn compare(&self, channel: Channel) -> $W {
+                    match channel {
+                        $(Channel::$C => self.tim.$ccrx().read().bits() as $W,)+
+                        #[allow(unreachable_patterns)]
+                        _ => panic!("channel not available on this timer"),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+compare_channels! {
+    TIM1: u16, [C1 => ccr1, C2 => ccr2, C3 => ccr3, C4 => ccr4],
+    TIM3: u16, [C1 => ccr1, C2 => ccr2, C3 => ccr3, C4 => ccr4],
+    TIM14: u16, [C1 => ccr1],
+    TIM16: u16, [C1 => ccr1],
+    TIM17: u16, [C1 => ccr1],
+}
+
+master_mode! {
+    TIM1,
+    TIM3,
+}
+
+slave_mode! {
+    TIM1: true,
+    TIM3: true,
+}
+
+repetition_counter! {
+    TIM1,
+    TIM16,
+    TIM17,
+}
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+master_mode! {
+    TIM2,
+}
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+slave_mode! {
+    TIM2: true,
+}
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+compare_channels! {
+    TIM2: u32, [C1 => ccr1, C2 => ccr2, C3 => ccr3, C4 => ccr4],
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+master_mode! {
+    TIM6,
+    TIM15,
+}
+
+// TIM15 has no external trigger input
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+slave_mode! {
+    TIM15: false,
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+compare_channels! {
+    TIM15: u16, [C1 => ccr1, C2 => ccr2],
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+repetition_counter! {
+    TIM15,
+}
+
+#[cfg(any(
+    feature = "stm32f030xc",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+master_mode! {
+    TIM7,
+}
+
+// Interrupt line of the timers in the NVIC
+macro_rules! timer_interrupt {
+    ($($TIMX:ident: $IRQ:ident,)+) => {
+        $(
+            impl Timer<$TIMX> {
+                /// Returns the interrupt line of the timer
+                pub fn interrupt(&self) -> Interrupt {
+                    Interrupt::$IRQ
+                }
+
+                /// Returns true if the timer interrupt is pending in the NVIC
+                pub fn is_pending(&self) -> bool {
+                    NVIC::is_pending(Interrupt::$IRQ)
                 }
 
-                /// Return `Ok` if the timer has wrapped
-                /// Automatically clears the flag and restarts the time
-                fn wait(&mut self) -> nb::Result<(), Void> {
-                    if self.tim.sr.read().uif().bit_is_clear() {
-                        Err(nb::Error::WouldBlock)
-                    } else {
-                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
-                        Ok(())
-                    }
+                /// Clears a pending timer interrupt in the NVIC
+                ///
+                /// Call this after setting up the timer and before unmasking the
+                /// interrupt, so no stale request fires immediately.
+                pub fn unpend(&self) {
+                    NVIC::unpend(Interrupt::$IRQ)
                 }
             }
-
-            impl Periodic for Timer<$TIM> {}
         )+
-    }
+    };
 }
 
-timers! {
-    TIM1: (tim1, tim1en, tim1rst, apb2enr, apb2rstr),
-    TIM3: (tim3, tim3en, tim3rst, apb1enr, apb1rstr),
-    TIM14: (tim14, tim14en, tim14rst, apb1enr, apb1rstr),
-    TIM16: (tim16, tim16en, tim16rst, apb2enr, apb2rstr),
-    TIM17: (tim17, tim17en, tim17rst, apb2enr, apb2rstr),
+timer_interrupt! {
+    TIM1: TIM1_BRK_UP_TRG_COM,
+    TIM3: TIM3,
+    TIM14: TIM14,
+    TIM16: TIM16,
+    TIM17: TIM17,
 }
 
 #[cfg(any(
@@ -245,8 +1481,8 @@ timers! {
     feature = "stm32f091",
     feature = "stm32f098",
 ))]
-timers! {
-    TIM2: (tim2, tim2en, tim2rst, apb1enr, apb1rstr),
+timer_interrupt! {
+    TIM2: TIM2,
 }
 
 #[cfg(any(
@@ -261,36 +1497,136 @@ timers! {
     feature = "stm32f091",
     feature = "stm32f098",
 ))]
-timers! {
-    TIM6: (tim6, tim6en, tim6rst, apb1enr, apb1rstr),
-    TIM15: (tim15, tim15en, tim15rst, apb2enr, apb2rstr),
+timer_interrupt! {
+    TIM15: TIM15,
 }
 
+// TIM6 shares its interrupt with the DAC on devices which have one
 #[cfg(any(
+    feature = "stm32f030x8",
     feature = "stm32f030xc",
-    feature = "stm32f070xb",
+    feature = "stm32f070xb"
+))]
+timer_interrupt! {
+    TIM6: TIM6,
+}
+
+#[cfg(any(
+    feature = "stm32f051",
+    feature = "stm32f058",
     feature = "stm32f071",
     feature = "stm32f072",
     feature = "stm32f078",
     feature = "stm32f091",
     feature = "stm32f098",
 ))]
-timers! {
-    TIM7: (tim7, tim7en, tim7rst, apb1enr, apb1rstr),
+timer_interrupt! {
+    TIM6: TIM6_DAC,
+}
+
+// The PAC of the STM32F030 and STM32F070 has no TIM7 interrupt
+#[cfg(any(
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+timer_interrupt! {
+    TIM7: TIM7,
 }
 
 use crate::gpio::{AF0, AF1, AF2, AF4, AF5};
+// Only the TIM15 CH1N on PB15 uses AF3
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+use crate::gpio::AF3;
 
 use crate::gpio::{gpioa::*, gpiob::*, Alternate};
 
 // Output channels marker traits
+/// Pin usable as channel 1 of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as channel 1 of `{TIM}`",
+    note = "check that the pin is routed to this channel and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait PinC1<TIM> {}
+/// Pin usable as complementary channel 1 of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as complementary channel 1 of `{TIM}`",
+    note = "check that the pin is routed to this channel and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait PinC1N<TIM> {}
+/// Pin usable as channel 2 of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as channel 2 of `{TIM}`",
+    note = "check that the pin is routed to this channel and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait PinC2<TIM> {}
+/// Pin usable as complementary channel 2 of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as complementary channel 2 of `{TIM}`",
+    note = "check that the pin is routed to this channel and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait PinC2N<TIM> {}
+/// Pin usable as channel 3 of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as channel 3 of `{TIM}`",
+    note = "check that the pin is routed to this channel and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait PinC3<TIM> {}
+/// Pin usable as complementary channel 3 of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as complementary channel 3 of `{TIM}`",
+    note = "check that the pin is routed to this channel and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait PinC3N<TIM> {}
+/// Pin usable as channel 4 of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as channel 4 of `{TIM}`",
+    note = "check that the pin is routed to this channel and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
 pub trait PinC4<TIM> {}
+/// Pin usable as break input of timer `TIM`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as break input of `{TIM}`",
+    note = "check that the pin is routed to the break input and set to the matching alternate function (`into_alternate_afN()`) in the datasheet"
+)]
+pub trait PinBkin<TIM> {}
+
+/// Placeholder for a channel which is not connected to any pin
+///
+/// Lets a PWM constructor configure a channel used only internally, e.g. the
+/// compare event of TIM1 channel 4 as ADC trigger.
+pub struct NoPin;
+
+macro_rules! no_pin_impl {
+    ( $( $TIM:ident: ($($PINC:ident),+), )+ ) => {
+        $(
+            $(
+                impl $PINC<$TIM> for NoPin {}
+            )+
+        )+
+    };
+}
+
+no_pin_impl!(
+    TIM1: (PinC1, PinC2, PinC3, PinC4),
+    TIM3: (PinC1, PinC2, PinC3, PinC4),
+    TIM14: (PinC1),
+    TIM16: (PinC1),
+    TIM17: (PinC1),
+);
 
 macro_rules! channel_impl {
     ( $( $TIM:ident, $PINC:ident, $PINX:ident, $MODE:ident<$AF:ident>; )+ ) => {
@@ -311,6 +1647,8 @@ channel_impl!(
     TIM1, PinC3N, PB1, Alternate<AF2>;
     TIM1, PinC3N, PB15, Alternate<AF2>;
     TIM1, PinC4, PA11, Alternate<AF2>;
+    TIM1, PinBkin, PA6, Alternate<AF2>;
+    TIM1, PinBkin, PB12, Alternate<AF2>;
 
     TIM3, PinC1, PA6, Alternate<AF1>;
     TIM3, PinC2, PA7, Alternate<AF1>;
@@ -328,9 +1666,55 @@ channel_impl!(
     TIM16, PinC1, PA6, Alternate<AF5>;
     TIM16, PinC1, PB8, Alternate<AF2>;
     TIM16, PinC1N, PB6, Alternate<AF2>;
+    TIM16, PinBkin, PB5, Alternate<AF2>;
 
     TIM17, PinC1, PA7, Alternate<AF5>;
     TIM17, PinC1, PB9, Alternate<AF2>;
+    TIM17, PinBkin, PA10, Alternate<AF0>;
+    TIM17, PinBkin, PB4, Alternate<AF2>;
+);
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+channel_impl!(
+    TIM2, PinC1, PA0, Alternate<AF2>;
+    TIM2, PinC2, PA1, Alternate<AF2>;
+    TIM2, PinC3, PA2, Alternate<AF2>;
+    TIM2, PinC4, PA3, Alternate<AF2>;
+    TIM2, PinC1, PA5, Alternate<AF2>;
+    TIM2, PinC1, PA15, Alternate<AF2>;
+
+    TIM2, PinC2, PB3, Alternate<AF2>;
+    TIM2, PinC3, PB10, Alternate<AF2>;
+    TIM2, PinC4, PB11, Alternate<AF2>;
+);
+
+#[cfg(any(
+    feature = "stm32f031",
+    feature = "stm32f038",
+    feature = "stm32f042",
+    feature = "stm32f048",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+no_pin_impl!(
+    TIM2: (PinC1, PinC2, PinC3, PinC4),
 );
 
 #[cfg(any(
@@ -348,9 +1732,29 @@ channel_impl!(
 channel_impl!(
     TIM15, PinC1, PA2, Alternate<AF0>;
     TIM15, PinC2, PA3, Alternate<AF0>;
+    TIM15, PinC1N, PA1, Alternate<AF5>;
 
     TIM15, PinC1, PB14, Alternate<AF1>;
     TIM15, PinC2, PB15, Alternate<AF1>;
+    TIM15, PinC1N, PB15, Alternate<AF3>;
+
+    TIM15, PinBkin, PA9, Alternate<AF0>;
+);
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+no_pin_impl!(
+    TIM15: (PinC1, PinC2),
 );
 
 #[cfg(any(
@@ -405,6 +1809,7 @@ channel_impl!(
     TIM1, PinC2, PE11, Alternate<AF0>;
     TIM1, PinC3, PE13, Alternate<AF0>;
     TIM1, PinC4, PE14, Alternate<AF0>;
+    TIM1, PinBkin, PE15, Alternate<AF0>;
 
     TIM3, PinC1, PE3, Alternate<AF0>;
     TIM3, PinC2, PE4, Alternate<AF0>;
@@ -436,3 +1841,408 @@ channel_impl!(
     TIM15, PinC1, PF9, Alternate<AF0>;
     TIM15, PinC2, PF10, Alternate<AF0>;
 );
+
+/// Timer generating single pulses on channel 1
+///
+/// The counter runs once from zero to the end of the pulse and then stops.
+/// The output goes active `delay` ticks after the start and stays active for
+/// `width` ticks.
+pub struct OnePulse<TIM> {
+    clocks: Clocks,
+    tim: TIM,
+}
+
+/// Timer able to generate single pulses, see [pwm::one_pulse](crate::pwm::one_pulse)
+pub trait OnePulseTimer: Instance + Sized {
+    #[doc(hidden)]
+    fn into_one_pulse<PIN: PinC1<Self>>(
+        self,
+        pin: PIN,
+        rcc: &mut Rcc,
+        delay: MicroSecond,
+        width: MicroSecond,
+    ) -> OnePulse<Self>;
+}
+
+// the following timer have a main output switch, enable the outputs
+macro_rules! opm_moe {
+    (TIM1, $tim:expr) => {
+        $tim.bdtr.modify(|_, w| w.moe().set_bit());
+    };
+    (TIM15, $tim:expr) => {
+        $tim.bdtr.modify(|_, w| w.moe().set_bit());
+    };
+    (TIM16, $tim:expr) => {
+        $tim.bdtr.modify(|_, w| w.moe().set_bit());
+    };
+    (TIM17, $tim:expr) => {
+        $tim.bdtr.modify(|_, w| w.moe().set_bit());
+    };
+    ($_other:ident, $_tim:expr) => {};
+}
+
+macro_rules! one_pulse {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Configures the timer to generate single pulses on channel 1
+                ///
+                /// The counter is incremented with `resolution`, `delay` and
+                /// `width` are given in counter ticks. Pulses are started with
+                /// [trigger](OnePulse::trigger).
+                pub fn one_pulse<PIN, T>(
+                    self,
+                    _pin: PIN,
+                    resolution: T,
+                    delay: u16,
+                    width: u16,
+                ) -> OnePulse<$TIM>
+                where
+                    PIN: PinC1<$TIM>,
+                    T: Into<Hertz>,
+                {
+                    let tim = self.tim;
+                    tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    tim.cnt.reset();
+
+                    let psc = cast::u16(self.clocks.timclk().0 / resolution.into().0 - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+
+                    // Inactive until CCR1, active until ARR
+                    tim.ccmr1_output().modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode2());
+                    tim.ccer.modify(|_, w| w.cc1e().set_bit());
+                    opm_moe!($TIM, tim);
+
+                    let mut pulse = OnePulse {
+                        clocks: self.clocks,
+                        tim,
+                    };
+                    pulse.set_pulse(delay, width);
+                    pulse.tim.cr1.modify(|_, w| w.opm().set_bit());
+                    pulse
+                }
+            }
+
+            impl OnePulseTimer for $TIM {
+                fn into_one_pulse<PIN: PinC1<Self>>(
+                    self,
+                    pin: PIN,
+                    rcc: &mut Rcc,
+                    delay: MicroSecond,
+                    width: MicroSecond,
+                ) -> OnePulse<Self> {
+                    Self::enable_clock(rcc);
+                    let timer = Timer { clocks: rcc.clocks, tim: self };
+                    let mut pulse = timer.one_pulse(pin, rcc.clocks.timclk(), 0, 1);
+                    pulse.set_timing(delay, width);
+                    pulse
+                }
+            }
+
+            impl OnePulse<$TIM> {
+                /// Changes delay and width of the following pulses to durations
+                ///
+                /// Selects the finest resolution at which both fit into the
+                /// counter. Together they can be up to 2^32 timer clock cycles,
+                /// e.g. 89 seconds at 48MHz.
+                pub fn set_timing(&mut self, delay: MicroSecond, width: MicroSecond) {
+                    let timclk = u64::from(self.clocks.timclk().0);
+                    let cycles = |us: MicroSecond| {
+                        cast::u32(timclk * u64::from(us.0) / 1_000_000).unwrap()
+                    };
+                    let delay = cycles(delay);
+                    let width = cycles(width);
+
+                    let psc = cast::u16(delay.saturating_add(width) / (1 << 16)).unwrap();
+                    self.tim.psc.write(|w| w.psc().bits(psc));
+                    let tick = u32::from(psc) + 1;
+                    let width = (width / tick).max(1);
+                    self.set_pulse(cast::u16(delay / tick).unwrap(), cast::u16(width).unwrap());
+                }
+
+                /// Changes delay and width of the following pulses
+                ///
+                /// The pulse ends at the latest when the counter reaches 0xffff, so
+                /// if `delay + width` exceeds it the width is shortened.
+                #[allow(unused_unsafe)]
+                pub fn set_pulse(&mut self, delay: u16, width: u16) {
+                    let end = delay.saturating_add(width);
+                    self.tim.ccr1().write(|w| unsafe { w.ccr().bits(delay.into()) });
+                    self.tim.arr.write(|w| unsafe { w.bits(u32::from(end)) });
+
+                    // Trigger update event to load the registers
+                    self.tim.cr1.modify(|_, w| w.urs().set_bit());
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.tim.cr1.modify(|_, w| w.urs().clear_bit());
+                }
+
+                /// Starts a pulse
+                pub fn trigger(&mut self) {
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                /// Returns true while a pulse is generated
+                pub fn is_running(&self) -> bool {
+                    self.tim.cr1.read().cen().bit_is_set()
+                }
+
+                /// Returns the frequency the counter is incremented with
+                pub fn resolution(&self) -> Hertz {
+                    let psc = u32::from(self.tim.psc.read().psc().bits());
+                    Hertz(self.clocks.timclk().0 / (psc + 1))
+                }
+
+                /// Releases the TIM peripheral
+                pub fn release(self) -> $TIM {
+                    let tim = self.tim;
+                    tim.cr1.modify(|_, w| w.cen().clear_bit().opm().clear_bit());
+                    tim.ccer.modify(|_, w| w.cc1e().clear_bit());
+                    tim
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! one_pulse_trigger {
+    ($($TIM:ident,)+) => {
+        $(
+            impl OnePulse<$TIM> {
+                /// Starts pulses on edges of the channel 2 input
+                ///
+                /// Channel 2 is used as trigger input and can't be used for
+                /// anything else.
+                pub fn trigger_on<PIN>(&mut self, _pin: PIN, edge: Edge)
+                where
+                    PIN: PinC2<$TIM>,
+                {
+                    // CC2 as input on TI2
+                    self.tim.ccer.modify(|_, w| w.cc2e().clear_bit());
+                    self.tim
+                        .ccmr1_input()
+                        .modify(|r, w| unsafe { w.bits((r.bits() & !(0xff << 8)) | (0b01 << 8)) });
+                    self.tim.ccer.modify(|_, w| {
+                        w.cc2p()
+                            .bit(edge != Edge::Rising)
+                            .cc2np()
+                            .bit(edge == Edge::Both)
+                    });
+
+                    // Start the counter on TI2FP2
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !SMCR_MASK)
+                                | SMCR_SMS_TRIGGER
+                                | (SMCR_TS_TI2FP2 << SMCR_TS_SHIFT),
+                        )
+                    });
+                }
+            }
+        )+
+    };
+}
+
+/// Timer emitting a pulse and measuring the time until its echo
+///
+/// Channel 1 outputs the pulse, channel 2 captures the echo. Both happen in a
+/// single run of the counter, so the result has the full timer resolution.
+pub struct PulseEcho<TIM> {
+    clocks: Clocks,
+    tim: TIM,
+    width: u16,
+}
+
+macro_rules! pulse_echo {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Configures the timer for pulse/echo measurements
+                ///
+                /// The counter is incremented with `resolution`, the pulse on
+                /// `trigger_ch` is `width` ticks long and the echo is detected on
+                /// `edge` of `echo_ch`.
+                pub fn pulse_echo<TRIG, ECHO, T>(
+                    self,
+                    _trigger_ch: TRIG,
+                    _echo_ch: ECHO,
+                    resolution: T,
+                    width: u16,
+                    edge: Edge,
+                ) -> PulseEcho<$TIM>
+                where
+                    TRIG: PinC1<$TIM>,
+                    ECHO: PinC2<$TIM>,
+                    T: Into<Hertz>,
+                {
+                    let tim = self.tim;
+                    tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    tim.cnt.reset();
+
+                    let psc = cast::u16(self.clocks.timclk().0 / resolution.into().0 - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.cr1.modify(|_, w| w.urs().clear_bit());
+
+                    // CC1 output held inactive between measurements
+                    tim.ccmr1_output().modify(|_, w| w.oc1m().force_inactive());
+                    // CC2 as input on TI2
+                    tim.ccer.modify(|_, w| w.cc2e().clear_bit());
+                    tim.ccmr1_input()
+                        .modify(|r, w| unsafe { w.bits((r.bits() & !(0xff << 8)) | (0b01 << 8)) });
+                    tim.ccer.modify(|_, w| {
+                        w.cc1e()
+                            .set_bit()
+                            .cc2p()
+                            .bit(edge != Edge::Rising)
+                            .cc2np()
+                            .bit(edge == Edge::Both)
+                            .cc2e()
+                            .set_bit()
+                    });
+                    opm_moe!($TIM, tim);
+
+                    PulseEcho {
+                        clocks: self.clocks,
+                        tim,
+                        width,
+                    }
+                }
+            }
+
+            impl PulseEcho<$TIM> {
+                /// Emits a pulse and waits for the echo
+                ///
+                /// Returns the number of ticks from the start of the pulse to the
+                /// echo edge, or `None` if no echo arrived within `timeout` ticks,
+                /// also if `timeout` ends before the pulse.
+                #[allow(unused_unsafe)]
+                pub fn measure(&mut self, timeout: u16) -> Option<u16> {
+                    let tim = &self.tim;
+
+                    // Count once from 0 to timeout
+                    tim.arr.write(|w| unsafe { w.bits(u32::from(timeout)) });
+                    tim.ccr1().write(|w| unsafe { w.ccr().bits(self.width.into()) });
+                    tim.cnt.reset();
+                    // The flags are cleared by writing 0
+                    tim.sr.write(|w| unsafe { w.bits(0) });
+
+                    tim.ccmr1_output().modify(|_, w| w.oc1m().pwm_mode1());
+                    tim.cr1.modify(|_, w| w.opm().set_bit().cen().set_bit());
+
+                    // End of the pulse, keep the output inactive once the counter
+                    // stops and wraps to 0. A timeout shorter than the pulse ends the
+                    // measurement before the pulse does.
+                    loop {
+                        let sr = tim.sr.read();
+                        if sr.uif().bit_is_set() {
+                            tim.ccmr1_output().modify(|_, w| w.oc1m().force_inactive());
+                            return None;
+                        }
+                        if sr.cc1if().bit_is_set() {
+                            break;
+                        }
+                    }
+                    tim.ccmr1_output().modify(|_, w| w.oc1m().force_inactive());
+
+                    loop {
+                        let sr = tim.sr.read();
+                        if sr.cc2if().bit_is_set() {
+                            tim.cr1.modify(|_, w| w.cen().clear_bit());
+                            // Reading the capture register clears CC2IF
+                            return Some(tim.ccr2().read().ccr().bits() as u16);
+                        }
+                        if sr.uif().bit_is_set() {
+                            return None;
+                        }
+                    }
+                }
+
+                /// Changes the width of the following pulses
+                pub fn set_width(&mut self, width: u16) {
+                    self.width = width;
+                }
+
+                /// Returns the frequency the counter is incremented with
+                pub fn resolution(&self) -> Hertz {
+                    let psc = u32::from(self.tim.psc.read().psc().bits());
+                    Hertz(self.clocks.timclk().0 / (psc + 1))
+                }
+
+                /// Releases the TIM peripheral
+                pub fn release(self) -> $TIM {
+                    let tim = self.tim;
+                    tim.cr1.modify(|_, w| w.cen().clear_bit().opm().clear_bit());
+                    tim.ccer.modify(|_, w| w.cc1e().clear_bit().cc2e().clear_bit());
+                    tim
+                }
+            }
+        )+
+    };
+}
+
+// TIM14 has no one pulse mode
+one_pulse! {
+    TIM1,
+    TIM3,
+    TIM16,
+    TIM17,
+}
+
+pulse_echo! {
+    TIM1,
+    TIM3,
+}
+
+one_pulse_trigger! {
+    TIM1,
+    TIM3,
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+one_pulse! {
+    TIM15,
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+one_pulse_trigger! {
+    TIM15,
+}
+
+#[cfg(any(
+    feature = "stm32f030x8",
+    feature = "stm32f030xc",
+    feature = "stm32f051",
+    feature = "stm32f058",
+    feature = "stm32f070xb",
+    feature = "stm32f071",
+    feature = "stm32f072",
+    feature = "stm32f078",
+    feature = "stm32f091",
+    feature = "stm32f098",
+))]
+pulse_echo! {
+    TIM15,
+}
@@ -0,0 +1,145 @@
+//! Lightweight event tracing
+//!
+//! The Cortex-M0 core has no SWO output, so this module provides a small
+//! alternative: code locations are marked with one byte event codes which are
+//! sent out, together with a timestamp, over a spare USART running at a high
+//! baud rate or as a pulse pattern on a GPIO pin to be captured by a logic
+//! analyzer.
+//!
+//! Events are emitted inside a critical section, so traces from interrupt
+//! handlers never tear frames written by the main loop.
+//!
+//! # Example
+//! ``` no_run
+//! use stm32f0xx_hal as hal;
+//!
+//! use crate::hal::pac;
+//! use crate::hal::prelude::*;
+//! use crate::hal::serial::Serial;
+//! use crate::hal::trace::{SerialSink, Tracer};
+//!
+//! cortex_m::interrupt::free(|cs| {
+//!     let mut p = pac::Peripherals::take().unwrap();
+//!     let mut rcc = p.RCC.configure().sysclk(48.mhz()).freeze(&mut p.FLASH);
+//!
+//!     let gpioa = p.GPIOA.split(&mut rcc);
+//!     let tx = gpioa.pa9.into_alternate_af1(cs);
+//!
+//!     let serial = Serial::usart1tx(p.USART1, tx, 3_000_000.bps(), &mut rcc);
+//!
+//!     // Timestamp events with the free running counter of TIM2
+//!     let mut tracer = Tracer::new(SerialSink::new(serial), || unsafe {
+//!         (*pac::TIM2::ptr()).cnt.read().bits()
+//!     });
+//!
+//!     tracer.event(0x10);
+//! });
+//! ```
+
+use embedded_hal::{digital::v2::OutputPin, serial};
+
+/// Destination of trace events
+pub trait Sink {
+    /// Emits a single event
+    fn emit(&mut self, timestamp: u32, code: u8);
+}
+
+/// Sink sending events as three byte frames over a serial port
+///
+/// Each frame consists of the event code followed by the lower 16 bits of the
+/// timestamp in little endian order.
+pub struct SerialSink<TX> {
+    tx: TX,
+}
+
+impl<TX> SerialSink<TX>
+where
+    TX: serial::Write<u8>,
+{
+    /// Creates a sink writing to `tx`
+    pub fn new(tx: TX) -> Self {
+        SerialSink { tx }
+    }
+
+    /// Releases the serial port
+    pub fn release(self) -> TX {
+        self.tx
+    }
+}
+
+impl<TX> Sink for SerialSink<TX>
+where
+    TX: serial::Write<u8>,
+{
+    fn emit(&mut self, timestamp: u32, code: u8) {
+        let [lo, hi, _, _] = timestamp.to_le_bytes();
+        for byte in [code, lo, hi].iter() {
+            // A failing trace output must not affect the traced program
+            nb::block!(self.tx.write(*byte)).ok();
+        }
+    }
+}
+
+/// Sink emitting events as pulse trains on an output pin
+///
+/// Each event drives `code + 1` pulses onto the pin, the timestamp is left to
+/// the logic analyzer capturing the pin.
+pub struct PinSink<PIN> {
+    pin: PIN,
+}
+
+impl<PIN> PinSink<PIN>
+where
+    PIN: OutputPin,
+{
+    /// Creates a sink driving `pin`
+    pub fn new(mut pin: PIN) -> Self {
+        pin.set_low().ok();
+        PinSink { pin }
+    }
+
+    /// Releases the pin
+    pub fn release(self) -> PIN {
+        self.pin
+    }
+}
+
+impl<PIN> Sink for PinSink<PIN>
+where
+    PIN: OutputPin,
+{
+    fn emit(&mut self, _timestamp: u32, code: u8) {
+        for _ in 0..=code {
+            self.pin.set_high().ok();
+            self.pin.set_low().ok();
+        }
+    }
+}
+
+/// Event tracer
+pub struct Tracer<S> {
+    sink: S,
+    now: fn() -> u32,
+}
+
+impl<S> Tracer<S>
+where
+    S: Sink,
+{
+    /// Creates a tracer emitting to `sink`, timestamped by `now`
+    pub fn new(sink: S, now: fn() -> u32) -> Self {
+        Tracer { sink, now }
+    }
+
+    /// Emits the event `code`
+    pub fn event(&mut self, code: u8) {
+        let timestamp = (self.now)();
+        let sink = &mut self.sink;
+        cortex_m::interrupt::free(|_| sink.emit(timestamp, code));
+    }
+
+    /// Releases the sink
+    pub fn release(self) -> S {
+        self.sink
+    }
+}
@@ -108,7 +108,7 @@ tsc_pins!(
     gpiob::PB7<Alternate<AF3>> => (5_u8, 4_u8),
 );
 
-// all with a TSC minus 42 and 48
+// Group 6, all with a TSC minus 42 and 48
 #[cfg(any(
     feature = "stm32f051",
     feature = "stm32f058",
@@ -125,7 +125,7 @@ tsc_pins!(
     gpiob::PB14<Alternate<AF3>> => (6_u8, 4_u8),
 );
 
-// all with a TSC and gpioe
+// Group 7, all with a TSC and gpioe
 #[cfg(any(
     feature = "stm32f071",
     feature = "stm32f072",
@@ -140,7 +140,7 @@ tsc_pins!(
     gpioe::PE5<Alternate<AF3>> => (7_u8, 4_u8),
 );
 
-// all with a TSC and gpiod
+// Group 8, all with a TSC and gpiod
 #[cfg(any(
     feature = "stm32f071",
     feature = "stm32f072",
@@ -155,6 +155,21 @@ tsc_pins!(
     gpiod::PD15<Alternate<AF3>> => (8_u8, 4_u8),
 );
 
+/// Returns the offset of the counter register of `group` in the TSC register block
+#[cfg_attr(
+    not(any(
+        feature = "stm32f071",
+        feature = "stm32f072",
+        feature = "stm32f078",
+        feature = "stm32f091",
+        feature = "stm32f098"
+    )),
+    allow(dead_code)
+)]
+const fn iogcr_offset(group: u8) -> usize {
+    0x30 + 4 * group as usize
+}
+
 pub struct Tsc {
     tsc: TSC,
 }
@@ -223,6 +238,7 @@ impl Tsc {
     pub fn tsc(tsc: TSC, rcc: &mut Rcc, cfg: Option<Config>) -> Self {
         // Enable the peripheral clock
         rcc.regs.ahbenr.modify(|_, w| w.tscen().set_bit());
+        clock_event!(enabled, "TSC");
         rcc.regs.ahbrstr.modify(|_, w| w.tscrst().set_bit());
         rcc.regs.ahbrstr.modify(|_, w| w.tscrst().clear_bit());
 
@@ -387,6 +403,9 @@ impl Tsc {
     }
 
     /// Reads the tsc group count register
+    ///
+    /// Groups 7 and 8 are only available on F07x and F09x devices, unknown
+    /// groups read as 0.
     pub fn read_unchecked(&self, group: u8) -> u16 {
         match group {
             1 => self.tsc.iog1cr().read().cnt().bits(),
@@ -395,6 +414,22 @@ impl Tsc {
             4 => self.tsc.iog4cr().read().cnt().bits(),
             5 => self.tsc.iog5cr().read().cnt().bits(),
             6 => self.tsc.iog6cr().read().cnt().bits(),
+            #[cfg(any(
+                feature = "stm32f071",
+                feature = "stm32f072",
+                feature = "stm32f078",
+                feature = "stm32f091",
+                feature = "stm32f098"
+            ))]
+            7 | 8 => {
+                // The PAC only has the counters of groups 1 to 6, so these are read
+                // by their offset in the register block
+                let iogcr = (TSC::ptr() as usize + iogcr_offset(group)) as *const u32;
+                // NOTE(unsafe) atomic read with no side effects
+                let bits = unsafe { core::ptr::read_volatile(iogcr) };
+                // 14 bit counter
+                (bits & 0x3fff) as u16
+            }
             _ => 0,
         }
     }
@@ -428,3 +463,17 @@ impl Tsc {
         self.tsc
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_counter_offsets() {
+        // IOG1CR to IOG8CR from the register map of the reference manual
+        assert_eq!(iogcr_offset(1), 0x34);
+        assert_eq!(iogcr_offset(6), 0x48);
+        assert_eq!(iogcr_offset(7), 0x4c);
+        assert_eq!(iogcr_offset(8), 0x50);
+    }
+}
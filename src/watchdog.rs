@@ -24,6 +24,12 @@
 //! dbgmcu.apb1_fz.modify(|_, w| w.dbg_iwdg_stop().set_bit());
 //! ```
 //!
+//! The IWDG can't be frozen in Stop or Standby mode on the STM32F0, once
+//! started it keeps counting on the LSI while the core sleeps. A design which
+//! enters Stop mode has to wake up and feed the watchdog before the timeout,
+//! e.g. with the RTC wakeup timer or an EXTI line, and should size the timeout
+//! for the longest sleep plus the wakeup time and the LSI inaccuracy.
+//!
 //! # Example
 //! ``` no_run
 //! use stm32f0xx_hal as hal;
@@ -41,10 +47,23 @@
 //! // Whoops, got stuck, the watchdog issues a reset after 10 ms
 //! iwdg.feed();
 //! ```
-use embedded_hal::watchdog;
+use embedded_hal::{timer::CountDown, watchdog};
 
-use crate::pac::IWDG;
+use crate::pac::{IWDG, TIM14};
+use crate::rcc::Rcc;
 use crate::time::Hertz;
+use crate::timers::Timer;
+
+/// Nominal frequency of the LSI clocking the IWDG
+const LSI_NOMINAL: u32 = 40_000;
+
+/// Error returned by [measure_lsi]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// TIM14 captured no LSI edges in time
+    LsiTimeout,
+}
 
 /// Watchdog instance
 pub struct Watchdog {
@@ -66,13 +85,14 @@ pub struct IwdgTimeout {
     reload: u16,
 }
 
-impl From<Hertz> for IwdgTimeout {
-    /// This converts the value so it's usable by the IWDG
-    /// Due to conversion losses, the specified frequency is a maximum
+impl IwdgTimeout {
+    /// Calculates the timeout for an LSI running at `lsi`
     ///
-    /// It can also only represent values < 10000 Hertz
-    fn from(hz: Hertz) -> Self {
-        let mut time = 40_000 / 4 / hz.0;
+    /// Use this with a frequency obtained by [measure_lsi] to get accurate
+    /// timeouts despite the large spread of the LSI (30kHz to 60kHz).
+    /// Due to conversion losses, the specified frequency is a maximum.
+    pub fn with_lsi(hz: Hertz, lsi: Hertz) -> Self {
+        let mut time = lsi.0 / 4 / hz.0;
         let mut psc = 0;
         let mut reload = 0;
         while psc < 7 {
@@ -89,6 +109,163 @@ impl From<Hertz> for IwdgTimeout {
     }
 }
 
+impl From<Hertz> for IwdgTimeout {
+    /// This converts the value so it's usable by the IWDG, assuming the
+    /// nominal LSI frequency of 40kHz
+    /// Due to conversion losses, the specified frequency is a maximum
+    ///
+    /// It can also only represent values < 10000 Hertz
+    fn from(hz: Hertz) -> Self {
+        IwdgTimeout::with_lsi(hz, Hertz(LSI_NOMINAL))
+    }
+}
+
+/// Watchdog with an emulated early warning interrupt
+///
+/// A hardware timer is restarted on every feed together with the IWDG and
+/// times out shortly before the watchdog would reset the device. If the timer
+/// is listening for its `TimeOut` event, its interrupt handler serves as
+/// early warning, e.g. to log state before the inevitable reset.
+pub struct EarlyWarning<TIM> {
+    iwdg: Watchdog,
+    timer: Timer<TIM>,
+    warning: Hertz,
+}
+
+impl<TIM> EarlyWarning<TIM>
+where
+    Timer<TIM>: CountDown<Time = Hertz>,
+{
+    /// Combines the watchdog with a timer for the warning
+    pub fn new(iwdg: Watchdog, timer: Timer<TIM>) -> Self {
+        EarlyWarning {
+            iwdg,
+            timer,
+            warning: Hertz(0),
+        }
+    }
+
+    /// Starts the watchdog with `period` and the warning timer with `warning`
+    ///
+    /// `warning` has to be a higher frequency than `period` with enough margin
+    /// for the LSI inaccuracy, unless the period is calculated from a measured
+    /// LSI frequency (see [measure_lsi]).
+    pub fn start<T>(&mut self, period: T, warning: Hertz)
+    where
+        T: Into<IwdgTimeout>,
+    {
+        self.warning = warning;
+        watchdog::WatchdogEnable::start(&mut self.iwdg, period);
+        self.timer.start(warning);
+    }
+
+    /// Gives access to the warning timer, e.g. to clear its interrupt flag
+    pub fn timer(&mut self) -> &mut Timer<TIM> {
+        &mut self.timer
+    }
+
+    /// Releases the watchdog and the timer
+    pub fn release(self) -> (Watchdog, Timer<TIM>) {
+        (self.iwdg, self.timer)
+    }
+}
+
+impl<TIM> watchdog::Watchdog for EarlyWarning<TIM>
+where
+    Timer<TIM>: CountDown<Time = Hertz>,
+{
+    /// Feeds the watchdog and restarts the warning timer
+    fn feed(&mut self) {
+        watchdog::Watchdog::feed(&mut self.iwdg);
+        // Not started yet
+        if self.warning.0 != 0 {
+            self.timer.start(self.warning);
+        }
+    }
+}
+
+// RCC CFGR MCO field
+const CFGR_MCO_SHIFT: u32 = 24;
+const CFGR_MCO_MASK: u32 = 0b1111 << CFGR_MCO_SHIFT;
+const CFGR_MCO_LSI: u32 = 0b0010;
+
+// Number of LSI periods per capture and captures to average over
+const LSI_PERIODS_PER_CAPTURE: u32 = 8;
+const LSI_CAPTURES: u32 = 8;
+// Polls of the capture flag, far longer than 8 periods of the slowest LSI
+const LSI_CAPTURE_TIMEOUT: u32 = 0x10000;
+
+/// Measures the actual frequency of the LSI
+///
+/// The LSI is routed to the input of TIM14 through the MCO and measured
+/// against the timer clock, so the result is as accurate as the system clock
+/// (e.g. HSE or HSI48 with CRS). The LSI is left enabled, TIM14 is reset and
+/// the previous MCO selection restored afterwards, also when no edges were
+/// captured and [Error::LsiTimeout] is returned.
+pub fn measure_lsi(tim14: &mut TIM14, rcc: &mut Rcc) -> Result<Hertz, Error> {
+    // Switch on the LSI
+    rcc.regs.csr.modify(|_, w| w.lsion().set_bit());
+    while rcc.regs.csr.read().lsirdy().bit_is_clear() {}
+
+    // Output the LSI on the MCO, which can be captured by TIM14
+    let cfgr = rcc.regs.cfgr.read().bits();
+    rcc.regs.cfgr.modify(|r, w| unsafe {
+        w.bits((r.bits() & !CFGR_MCO_MASK) | (CFGR_MCO_LSI << CFGR_MCO_SHIFT))
+    });
+
+    // Reset TIM14 to a clean slate state
+    rcc.regs.apb1enr.modify(|_, w| w.tim14en().set_bit());
+    clock_event!(enabled, "TIM14");
+    rcc.regs.apb1rstr.modify(|_, w| w.tim14rst().set_bit());
+    rcc.regs.apb1rstr.modify(|_, w| w.tim14rst().clear_bit());
+
+    // TI1 remapped to MCO
+    tim14.or.write(|w| unsafe { w.bits(0b11) });
+    // CC1 as input on TI1, capture every 8th rising edge
+    tim14
+        .ccmr1_input()
+        .write(|w| unsafe { w.bits(0b01 | (0b11 << 2)) });
+    tim14.ccer.write(|w| w.cc1e().set_bit());
+    tim14.arr.write(|w| unsafe { w.bits(0xffff) });
+    tim14.cr1.modify(|_, w| w.cen().set_bit());
+
+    let capture = || {
+        if !(0..LSI_CAPTURE_TIMEOUT).any(|_| tim14.sr.read().cc1if().bit_is_set()) {
+            return None;
+        }
+        // Reading the capture register clears CC1IF
+        Some(tim14.ccr1().read().bits() as u16)
+    };
+
+    let measure = || {
+        // The first capture might be of an incomplete interval
+        capture()?;
+        let mut last = capture()?;
+        let mut ticks = 0_u32;
+        for _ in 0..LSI_CAPTURES {
+            let now = capture()?;
+            ticks += u32::from(now.wrapping_sub(last));
+            last = now;
+        }
+        Some(ticks)
+    };
+    let ticks = measure();
+
+    tim14.cr1.modify(|_, w| w.cen().clear_bit());
+    rcc.regs.apb1rstr.modify(|_, w| w.tim14rst().set_bit());
+    rcc.regs.apb1rstr.modify(|_, w| w.tim14rst().clear_bit());
+    rcc.regs.apb1enr.modify(|_, w| w.tim14en().clear_bit());
+    clock_event!(disabled, "TIM14");
+    rcc.regs
+        .cfgr
+        .modify(|r, w| unsafe { w.bits((r.bits() & !CFGR_MCO_MASK) | (cfgr & CFGR_MCO_MASK)) });
+
+    let ticks = ticks.ok_or(Error::LsiTimeout)?;
+    let tclk = u64::from(rcc.clocks.timclk().0);
+    let periods = u64::from(LSI_PERIODS_PER_CAPTURE * LSI_CAPTURES);
+    Ok(Hertz((tclk * periods / u64::from(ticks)) as u32))
+}
+
 impl Watchdog {
     pub fn new(iwdg: IWDG) -> Self {
         Self { iwdg }
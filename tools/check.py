@@ -28,6 +28,9 @@ def main():
 
     crate_info = cargo_meta["packages"][0]
 
+    # Features which don't select a device, checked together with one
+    device_less = ["power-profiling", "gpio-af-names", "rtic", "rtic-monotonic", "fugit", "defmt"]
+
     features = [
         "{},rt,stm32-usbd".format(x)
         for x in crate_info["features"].keys()
@@ -35,7 +38,8 @@ def main():
         and x != "rt"
         and x != "stm32f030"
         and x != "stm32-usbd"
-    ]
+        and x not in device_less
+    ] + ["stm32f072,{},rt,stm32-usbd".format(x) for x in device_less]
 
     if 'size_check' in sys.argv:
         cargo_cmd = ['cargo', 'build', '--release']